@@ -1,200 +1,641 @@
-use log::{debug, info, error};
-use serialport::SerialPort;
-use std::io::{Read, Write};
-use std::fs::OpenOptions;
+use crate::boot_profile::BootProfile;
+use crate::boot_timing::{BootTiming, SlowMilestone, BOOT_COMMAND};
+use crate::catalog::{Catalog, Check, Decoder, Phase};
+use crate::config::Config;
+use crate::console_output::{ConsoleOutput, LineMatcher};
+use crate::environment::{Environment, RedundantFlags};
+use crate::expect::{self, Expect};
+use crate::factory_data::{self, FactoryData};
+use crate::iprid;
+use crate::jig::{self, power_off_dut, power_on_dut};
+use crate::mtd;
+use crate::nand::BadBlocks;
+use crate::nor::{self, NorFlash};
+use crate::reset::{self, Start, SPL_BANNER};
+use crate::transport::ConsoleTransport;
+use chrono::{DateTime, Utc};
+use log::info;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
 
-#[derive(Default)]
-struct CheckInfo {
-    not_expected: Option<&'static str>,
-    expected: Option<&'static str>,
-    message: &'static str,
-    instructions: &'static str,
-    command: Option<&'static str>,
+/// Prompt of the U-Boot shell at the start of a line, with or without the
+/// trailing space. Keys typed to stop autoboot may be echoed after it.
+pub static U_BOOT_PROMPT: &str = r"(?m)^=> ?";
+/// Prompt of the U-Boot shell ending the output of a command, which may not
+/// end with a newline. "=>" within the output, e.g. in the ASCII column of
+/// `md.b` or in kernel messages after a failed boot, is no prompt.
+pub static U_BOOT_COMMAND_PROMPT: &str = r"=> ?\z";
+/// Prompt of a Linux shell at the start of a line, e.g. `root@gateway:~# `.
+static LINUX_PROMPT: &str = r"(?m)^[^\s#$]+[#$] ";
+/// Kernel message printed asynchronously on the Linux console.
+static KERNEL_MESSAGE: &str = r"\[\s*\d+\.\d+\] [^\n]*\n";
+static LOGIN_PROMPT: &str = "login:";
+static KERNEL_PANIC_END: &str = "---[ end Kernel panic";
+static PASSWORD_PROMPT: &str = "Password:";
+static LOGIN_INCORRECT: &str = "Login incorrect";
+static IPRID_MISMATCH: &str = "Scanned IPRID does not match the unit";
+/// Values holding the IPRID of the unit and where they are read from.
+static UNIT_IPRIDS: [(&str, &str); 2] = [
+    ("iprid", "factory data"),
+    ("linuxmoduleid", "U-Boot environment"),
+];
+static INSTRUCTIONS_FAULTY: &str = "Linux Module (probably) faulty, return to UniElec";
+/// Time without any output after which a powered on DUT is considered dead.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Station-side failure which prevents a diagnosis of the DUT.
+#[derive(Debug)]
+pub enum Error {
+    Jig(jig::Error),
+    Write(std::io::Error),
+    Read(std::io::Error),
 }
 
-#[derive(Default)]
-pub struct Diagnosis {
-    pub message: &'static str,
-    pub instructions: Option<&'static str>,
-    pub healthy: bool,
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Jig(e) => e.fmt(f),
+            Error::Write(e) => write!(f, "Failed to write to console: {e}"),
+            Error::Read(e) => write!(f, "Failed to read from console: {e}"),
+        }
+    }
 }
 
-static INSTRUCTIONS_LM: &str = "Linux Module (probably) faulty, return to UniElec";
-static INSTRUCTIONS_BUTTON: &str = "Check button";
-
-pub fn analyze(serial_port: &mut Box<dyn SerialPort>, lm_id: &str) -> Diagnosis {
-    let early_check_info = vec![
-        CheckInfo {
-            not_expected: Some("SPL: failed to boot from all boot devices"),
-            message: "U-Boot corrupt",
-            instructions: INSTRUCTIONS_LM,
-            ..Default::default()
-        },
-        CheckInfo {
-            expected: Some("U-Boot SPL"),
-            message: "No or wrong U-Boot detected",
-            instructions: INSTRUCTIONS_LM,
-            ..Default::default()
-        },
-        CheckInfo {
-            expected: Some("DRAM:  128 MiB"),
-            message: "Wrong RAM size detected",
-            instructions: INSTRUCTIONS_LM,
-            ..Default::default()
-        },
-        CheckInfo {
-            not_expected: Some("F-Data:Magic value not correct"),
-            expected: Some("F-Data:factory-data version 1 detected"),
-            message: "Factory data missing",
-            instructions: INSTRUCTIONS_LM,
-            ..Default::default()
-        },
-        CheckInfo {
-            expected: Some("Net:   eth0: eth@10110000"),
-            message: "Ethernet could not be initialized",
-            instructions: INSTRUCTIONS_LM,
-            ..Default::default()
-        },
-        CheckInfo {
-            expected: Some("=>"),
-            message: "Could not enter U-Boot shell",
-            instructions: INSTRUCTIONS_LM,
-            ..Default::default()
-        },
-    ];
-    let console_output = enter_u_boot(serial_port, lm_id);
-
-    for info in early_check_info {
-        if info
-            .not_expected
-            .is_some_and(|x| console_output.contains(x))
-            || info.expected.is_some_and(|x| !console_output.contains(x))
-        {
-            log_issue(info.message, info.instructions);
+impl std::error::Error for Error {}
 
-            return Diagnosis {
-                message: info.message,
-                instructions: Some(info.instructions),
-                healthy: false,
-            };
+impl From<jig::Error> for Error {
+    fn from(e: jig::Error) -> Self {
+        Error::Jig(e)
+    }
+}
+
+impl From<expect::Error> for Error {
+    fn from(e: expect::Error) -> Self {
+        match e {
+            expect::Error::Write(e) => Error::Write(e),
+            expect::Error::Read(e) => Error::Read(e),
         }
     }
+}
 
-    let u_boot_check_info = vec![
-        CheckInfo {
-            command: Some("mtd list"),
-            not_expected: Some("Could not find a valid device for spi0.1"),
-            expected: Some("spi-nand0"),
-            message: "NAND flash not detected",
-            instructions: INSTRUCTIONS_LM,
-        },
-        CheckInfo {
-            command: Some("gpio input PA11"),
-            not_expected: Some("gpio: pin PA11 (gpio 11) value is 0"),
-            expected: Some("gpio: pin PA11 (gpio 11) value is 1"),
-            message: "Button stuck",
-            instructions: INSTRUCTIONS_BUTTON,
-        },
-    ];
-
-    for info in u_boot_check_info {
-        if !run_u_boot_check(serial_port, &info, lm_id) {
-            log_issue(info.message, info.instructions);
-
-            return Diagnosis {
-                message: info.message,
-                instructions: Some(info.instructions),
-                healthy: false,
-            };
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+#[derive(Serialize)]
+pub struct CheckResult {
+    pub id: String,
+    pub outcome: Outcome,
+    pub message: String,
+    pub instructions: String,
+    /// Console output that decided the outcome.
+    pub excerpt: Option<String>,
+    /// Values captured by named groups of the check's `expected` regex.
+    pub values: BTreeMap<String, String>,
+}
+
+impl CheckResult {
+    fn new(check: &Check, outcome: Outcome, excerpt: Option<String>) -> CheckResult {
+        CheckResult {
+            id: check.id.clone(),
+            outcome,
+            message: check.message.clone(),
+            instructions: check.instructions.clone(),
+            excerpt,
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Result of a check whose command did not finish in time.
+    fn timed_out(check: &Check, command: &str, console_output: &str) -> CheckResult {
+        CheckResult {
+            message: format!("Timed out in phase {} running \"{command}\"", check.phase),
+            ..CheckResult::new(check, Outcome::Fail, last_line(console_output))
+        }
+    }
+
+    /// Result of a check whose command was interrupted by a reset of the DUT.
+    fn reset_running(check: &Check, command: &str, resets: &[Start]) -> CheckResult {
+        CheckResult {
+            message: format!(
+                "Unexpected reset in phase {} running \"{command}\"",
+                check.phase
+            ),
+            values: reset::values(resets),
+            ..CheckResult::new(check, Outcome::Fail, Some(reset::excerpt(resets)))
         }
     }
 
+    /// Result of resets of the DUT while no command was running.
+    fn phase_reset(phase: Phase, message: String, resets: &[Start]) -> CheckResult {
+        CheckResult {
+            id: format!("{phase}-reset"),
+            outcome: Outcome::Fail,
+            message,
+            instructions: String::from(INSTRUCTIONS_FAULTY),
+            excerpt: Some(reset::excerpt(resets)),
+            values: reset::values(resets),
+        }
+    }
+
+    /// Result of a milestone the DUT reached later than allowed.
+    fn slow(slow: SlowMilestone) -> CheckResult {
+        CheckResult {
+            id: format!("slow-{}", slow.milestone.name()),
+            outcome: Outcome::Fail,
+            message: format!(
+                "Slow boot: {} after {} ms (limit {} ms)",
+                slow.milestone, slow.elapsed_ms, slow.limit_ms
+            ),
+            instructions: String::from(INSTRUCTIONS_FAULTY),
+            excerpt: None,
+            values: BTreeMap::from([
+                (String::from("elapsed_ms"), slow.elapsed_ms.to_string()),
+                (String::from("limit_ms"), slow.limit_ms.to_string()),
+            ]),
+        }
+    }
 
-    Diagnosis {
-        message: "No issues found",
-        healthy: true,
-        ..Default::default()
+    /// Result of a phase which did not reach its end in time.
+    fn phase_timed_out(phase: Phase, console_output: &str) -> CheckResult {
+        CheckResult {
+            id: format!("{phase}-timeout"),
+            outcome: Outcome::Fail,
+            message: format!("Timed out in phase {phase}"),
+            instructions: String::from(INSTRUCTIONS_FAULTY),
+            excerpt: last_line(console_output),
+            values: BTreeMap::new(),
+        }
     }
 }
 
-fn remove_non_printable(s: &str) -> String {
-    s.chars()
-        .filter(|&c| c.is_ascii_graphic() || c.is_ascii_whitespace())
-        .collect()
+/// Why reading the console output stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stop {
+    /// The expected end of the output was seen.
+    Done,
+    /// The DUT stopped sending.
+    Idle,
+    /// The deadline passed.
+    Deadline,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Sent to the DUT.
+    Tx,
+    /// Received from the DUT.
+    Rx,
 }
 
-fn send(serial_port: &mut Box<dyn SerialPort>, buf: &[u8]) -> Result<(), serialport::Error> {
-    serial_port.write_all(buf)?;
-    serial_port.flush()?;
-    Ok(())
+#[derive(Serialize)]
+pub struct TranscriptEntry {
+    pub time: DateTime<Utc>,
+    pub direction: Direction,
+    pub text: String,
 }
 
-fn receive(serial_port: &mut Box<dyn SerialPort>, lm_id: &str) -> Option<String> {
-    let mut buf = [0; 1000];
-    let bytes_read = serial_port.read(&mut buf).unwrap_or(0);
-    if bytes_read == 0 {
-        return None;
+#[derive(Default, Serialize)]
+pub struct Diagnosis {
+    /// Primary fault, i.e. the first failed check in catalog order.
+    pub message: String,
+    pub instructions: Option<String>,
+    pub healthy: bool,
+    pub results: Vec<CheckResult>,
+    pub boot_profile: BootProfile,
+    pub boot_timing: BootTiming,
+    /// Everything sent to and received from the DUT.
+    pub transcript: Vec<TranscriptEntry>,
+}
+
+impl Diagnosis {
+    fn new(
+        results: Vec<CheckResult>,
+        boot_profile: BootProfile,
+        boot_timing: BootTiming,
+        transcript: Vec<TranscriptEntry>,
+    ) -> Diagnosis {
+        match results.iter().find(|r| r.outcome == Outcome::Fail) {
+            Some(primary) => Diagnosis {
+                message: primary.message.clone(),
+                instructions: Some(primary.instructions.clone()),
+                healthy: false,
+                results,
+                boot_profile,
+                boot_timing,
+                transcript,
+            },
+            None => Diagnosis {
+                message: String::from("No issues found"),
+                instructions: None,
+                healthy: true,
+                results,
+                boot_profile,
+                boot_timing,
+                transcript,
+            },
+        }
+    }
+
+    /// Compares the IPRID scanned from the label with the IPRIDs read from the
+    /// unit, i.e. the value `iprid` decoded from the factory data and the value
+    /// `linuxmoduleid` of the U-Boot environment.
+    ///
+    /// A mismatch becomes the primary fault, as the diagnosis would otherwise
+    /// be recorded for the wrong unit. An IPRID which could not be read is not
+    /// compared, it is reported by its check.
+    pub fn verify_iprid(&mut self, scanned: &str) {
+        let Some((source, unit)) = UNIT_IPRIDS.iter().find_map(|&(name, source)| {
+            self.results
+                .iter()
+                .find_map(|r| r.values.get(name))
+                .filter(|unit| iprid::normalize(unit) != iprid::normalize(scanned))
+                .map(|unit| (source, unit.clone()))
+        }) else {
+            return;
+        };
+
+        let instructions = format!("Check the label, the unit reports IPRID {unit}");
+        let result = CheckResult {
+            id: String::from("iprid-match"),
+            outcome: Outcome::Fail,
+            message: String::from(IPRID_MISMATCH),
+            instructions: instructions.clone(),
+            excerpt: Some(format!("scanned {scanned}, {source} {unit}")),
+            values: BTreeMap::from([
+                (String::from("scanned_iprid"), String::from(scanned)),
+                (String::from("unit_iprid"), unit),
+            ]),
+        };
+        log_issue(&result.message, &result.instructions);
+
+        self.message = String::from(IPRID_MISMATCH);
+        self.instructions = Some(instructions);
+        self.healthy = false;
+        self.results.insert(0, result);
     }
-    let s = remove_non_printable(&String::from_utf8_lossy(&buf));
-    if s.is_empty() {
-        return None;
+
+    /// Returns the failed checks, primary fault first.
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.results.iter().filter(|r| r.outcome == Outcome::Fail)
     }
-    debug!("{s}");
-    std::io::stdout().flush().expect("Failed to flush stdout");
+}
 
-    let file_name = format!("{}.txt", lm_id);
-    if let Ok(mut file) = OpenOptions::new().append(true).open(&file_name) {
-        if let Err(e) = writeln!(file, "{}", s) {
-            error!("ailed to append s to file: {}", e);
-        }
-    } else {
-        error!("failed to open the log file");
+/// Powers on the DUT, analyzes it and powers it off again, unless the power is
+/// switched externally.
+///
+/// # Errors
+///
+/// Will return `Err` if the jig or the console fails.
+pub fn diagnose(
+    console: &mut dyn ConsoleTransport,
+    catalog: &Catalog,
+    config: &Config,
+) -> Result<Diagnosis, Error> {
+    if !config.power_control {
+        return analyze(console, catalog, config, None);
     }
 
-    Some(s)
+    let powered_on = power_on_dut(console, config.invert_rts)?;
+    let diagnosis = analyze(console, catalog, config, Some(powered_on));
+    let powered_off = power_off_dut(console, config.invert_rts);
+
+    let diagnosis = diagnosis?;
+    powered_off?;
+    Ok(diagnosis)
 }
 
-fn enter_u_boot(serial_port: &mut Box<dyn SerialPort>, lm_id: &str) -> String {
-    let mut console_output = String::new();
-    let mut timeout_counter = 0;
+/// Runs the checks of `catalog` against the DUT, which was powered on at
+/// `powered_on` or, if `None`, externally.
+///
+/// Unless `config.exhaustive` is set, the analysis stops at the first failed
+/// check and all remaining checks are reported as skipped. The network checks
+/// only run if `config.network` is set, the kernel and Linux checks only if
+/// `config.linux` is set.
+///
+/// # Errors
+///
+/// Will return `Err` if communication with the DUT fails on the station side.
+///
+/// # Panics
+///
+/// Panics if something unexpected happens.
+pub fn analyze(
+    console: &mut dyn ConsoleTransport,
+    catalog: &Catalog,
+    config: &Config,
+    powered_on: Option<DateTime<Utc>>,
+) -> Result<Diagnosis, Error> {
+    let mut session = Expect::new(console, expect::regex(U_BOOT_PROMPT));
+    let mut results = Vec::new();
+    let mut failed = false;
+
+    let timeout = Duration::from_secs(config.u_boot_timeout);
+    let (console_output, stop) = enter_u_boot(&mut session, timeout)?;
+    let boot_profile = BootProfile::parse(console_output.text());
+    let prompt = stop == Stop::Done;
+    // The first start is the power on
+    let resets: Vec<Start> = reset::find_starts(console_output.text())
+        .into_iter()
+        .skip(1)
+        .collect();
+    if !resets.is_empty() {
+        let message = if prompt {
+            format!(
+                "Unexpected reset in phase {} ({})",
+                Phase::Early,
+                reset::count(&resets)
+            )
+        } else {
+            format!("Boot loop ({})", reset::count(&resets))
+        };
+        results.push(CheckResult::phase_reset(Phase::Early, message, &resets));
+        failed = true;
+    } else if stop == Stop::Deadline {
+        results.push(CheckResult::phase_timed_out(
+            Phase::Early,
+            console_output.text(),
+        ));
+        failed = true;
+    }
 
-    loop {
-        send(serial_port, b"x").expect("Failed to write to serial port");
+    for check in catalog.phase(Phase::Early) {
+        let result = if failed && !config.exhaustive {
+            CheckResult::new(check, Outcome::Skipped, None)
+        } else {
+            evaluate(check, &console_output, false, config)
+        };
+        failed |= result.outcome == Outcome::Fail;
+        results.push(result);
+    }
 
-        if let Some(s) = receive(serial_port, lm_id) {
-            console_output += s.as_str();
-            timeout_counter = 0;
+    let network_checks = catalog.phase(Phase::Network).filter(|_| config.network);
+    for check in catalog.phase(Phase::UBoot).chain(network_checks) {
+        let result = if !prompt || (failed && !config.exhaustive) {
+            CheckResult::new(check, Outcome::Skipped, None)
         } else {
-            timeout_counter += 1;
+            let command = check.command.as_deref().expect("Missing U-Boot command");
+            let command = match check.phase {
+                Phase::Network => network_command(command, config),
+                _ => command.to_string(),
+            };
+            let command = command.as_str();
+            let timeout = command_timeout(check, config);
+            let (console_output, stop) = run_u_boot_cmd(&mut session, command, timeout)?;
+            let resets = reset::find_starts(console_output.text());
+            if !resets.is_empty() {
+                CheckResult::reset_running(check, command, &resets)
+            } else if stop == Stop::Deadline {
+                CheckResult::timed_out(check, command, console_output.text())
+            } else {
+                evaluate(check, &console_output, true, config)
+            }
+        };
+        failed |= result.outcome == Outcome::Fail;
+        results.push(result);
+    }
+
+    if config.linux {
+        let linux_results = analyze_linux(&mut session, catalog, config, prompt, failed)?;
+        results.extend(linux_results);
+    }
+
+    let transcript = session.into_transcript();
+    let boot_timing = BootTiming::measure(powered_on, &transcript);
+    results.extend(
+        boot_timing
+            .slow(&config.boot_time_limits)
+            .into_iter()
+            .map(CheckResult::slow),
+    );
+
+    for result in results.iter().filter(|r| r.outcome == Outcome::Fail) {
+        log_issue(&result.message, &result.instructions);
+    }
+
+    Ok(Diagnosis::new(
+        results,
+        boot_profile,
+        boot_timing,
+        transcript,
+    ))
+}
+
+/// Boots Linux from the U-Boot shell and runs the kernel and Linux checks.
+fn analyze_linux(
+    session: &mut Expect,
+    catalog: &Catalog,
+    config: &Config,
+    prompt: bool,
+    mut failed: bool,
+) -> Result<Vec<CheckResult>, Error> {
+    let mut results = Vec::new();
+
+    let kernel_log = if !prompt || (failed && !config.exhaustive) {
+        None
+    } else {
+        let timeout = Duration::from_secs(config.linux_timeout);
+        let (kernel_log, stop) = boot_linux(session, timeout)?;
+        let resets = reset::find_starts(kernel_log.text());
+        if !resets.is_empty() {
+            let message = format!(
+                "Unexpected reset in phase {} ({})",
+                Phase::Kernel,
+                reset::count(&resets)
+            );
+            results.push(CheckResult::phase_reset(Phase::Kernel, message, &resets));
+            failed = true;
+        } else if stop == Stop::Deadline {
+            results.push(CheckResult::phase_timed_out(
+                Phase::Kernel,
+                kernel_log.text(),
+            ));
+            failed = true;
         }
+        Some(kernel_log)
+    };
+    for check in catalog.phase(Phase::Kernel) {
+        let result = match &kernel_log {
+            Some(kernel_log) if !failed || config.exhaustive => {
+                evaluate(check, kernel_log, false, config)
+            }
+            _ => CheckResult::new(check, Outcome::Skipped, None),
+        };
+        failed |= result.outcome == Outcome::Fail;
+        results.push(result);
+    }
 
-        if console_output.contains("=>") || timeout_counter >= 10 {
-            break;
+    let login = match &kernel_log {
+        Some(kernel_log)
+            if kernel_log.text().contains(LOGIN_PROMPT) && (!failed || config.exhaustive) =>
+        {
+            let (login, stop) = log_in(session, config)?;
+            if stop == Stop::Deadline {
+                results.push(CheckResult::phase_timed_out(Phase::Linux, login.text()));
+                failed = true;
+            }
+            Some(login)
         }
+        _ => None,
+    };
+    let shell = login.as_ref().is_some_and(|l| at_shell_prompt(l.text()));
+    for check in catalog.phase(Phase::Linux) {
+        let result = match (&login, &check.command) {
+            _ if failed && !config.exhaustive => CheckResult::new(check, Outcome::Skipped, None),
+            (Some(login), None) => evaluate(check, login, false, config),
+            (Some(_), Some(command)) if shell => {
+                let timeout = command_timeout(check, config);
+                let (console_output, stop) = run_linux_cmd(session, command, timeout)?;
+                let resets = reset::find_starts(console_output.text());
+                if !resets.is_empty() {
+                    CheckResult::reset_running(check, command, &resets)
+                } else if stop == Stop::Deadline {
+                    CheckResult::timed_out(check, command, console_output.text())
+                } else {
+                    evaluate(check, &console_output, true, config)
+                }
+            }
+            _ => CheckResult::new(check, Outcome::Skipped, None),
+        };
+        failed |= result.outcome == Outcome::Fail;
+        results.push(result);
     }
-    send(serial_port, b"\x03").expect("Failed to write to serial port"); // clear prompt
-    console_output
+
+    Ok(results)
 }
 
-fn run_u_boot_cmd(serial_port: &mut Box<dyn SerialPort>, cmd: &str, lm_id: &str) -> String {
-    send(serial_port, format!("{cmd}\n").as_bytes()).expect("Failed to write to serial port");
+/// Returns how long to wait for the output of the check's command.
+fn command_timeout(check: &Check, config: &Config) -> Duration {
+    Duration::from_secs(check.timeout.unwrap_or(config.command_timeout))
+}
 
-    let mut console_output = String::new();
-    let mut timeout_counter = 0;
+/// Fills in the IP addresses of the bench network, which network checks refer
+/// to as `{station_ip}` and `{dut_ip}`.
+fn network_command(command: &str, config: &Config) -> String {
+    command
+        .replace("{station_ip}", &config.station_ip)
+        .replace("{dut_ip}", &config.dut_ip)
+}
 
-    loop {
-        if let Some(s) = receive(serial_port, lm_id) {
-            console_output += s.as_str();
-            timeout_counter = 0;
-        } else {
-            timeout_counter += 1;
-        }
+/// Powers on into the U-Boot shell by sending keys until the prompt shows up,
+/// the DUT stays silent or `timeout` passes, e.g. in a boot loop.
+fn enter_u_boot(session: &mut Expect, timeout: Duration) -> Result<(ConsoleOutput, Stop), Error> {
+    let deadline = Instant::now() + timeout;
+    let result = session.wait_prompt_sending(b"x", IDLE_TIMEOUT, deadline)?;
 
-        if console_output.ends_with("=> ") || timeout_counter >= 10 {
-            break;
+    let stop = match result.matched {
+        Some(_) => {
+            session.set_prompt(expect::regex(U_BOOT_COMMAND_PROMPT));
+            session.interrupt()?; // clear keys typed after the prompt
+            Stop::Done
         }
+        None if Instant::now() >= deadline => Stop::Deadline,
+        None => Stop::Idle,
+    };
+    Ok((result.output, stop))
+}
+
+/// Waits for one of `patterns` in the output of a command.
+fn expect_output(
+    session: &mut Expect,
+    patterns: &[&Regex],
+    timeout: Duration,
+) -> Result<(ConsoleOutput, Stop), Error> {
+    let result = session.expect(patterns, Instant::now() + timeout)?;
+    let stop = match result.matched {
+        Some(_) => Stop::Done,
+        None => Stop::Deadline,
+    };
+    Ok((result.output, stop))
+}
+
+fn run_u_boot_cmd(
+    session: &mut Expect,
+    cmd: &str,
+    timeout: Duration,
+) -> Result<(ConsoleOutput, Stop), Error> {
+    session.send_line(cmd)?;
+
+    let prompt = session.prompt().clone();
+    expect_output(session, &[&prompt, &reset_banner()], timeout)
+}
+
+/// Boots Linux from the U-Boot shell and returns the console output until the
+/// login prompt, a kernel panic, a return to the U-Boot shell or `timeout`.
+fn boot_linux(session: &mut Expect, timeout: Duration) -> Result<(ConsoleOutput, Stop), Error> {
+    session.send_line(BOOT_COMMAND)?;
+
+    expect_output(
+        session,
+        &[
+            &expect::literal(LOGIN_PROMPT),
+            &expect::literal(KERNEL_PANIC_END),
+            &reset_banner(),
+            &expect::regex(U_BOOT_COMMAND_PROMPT),
+        ],
+        timeout,
+    )
+}
+
+/// Logs in on the Linux console and returns the console output until the
+/// shell prompt, a rejected login or the login timeout.
+///
+/// Kernel messages are ignored in the shell, they would hide its prompt.
+fn log_in(session: &mut Expect, config: &Config) -> Result<(ConsoleOutput, Stop), Error> {
+    let deadline = Instant::now() + Duration::from_secs(config.login_timeout);
+    session.set_prompt(expect::regex(LINUX_PROMPT));
+    let prompt = session.prompt().clone();
+    let password_prompt = expect::literal(PASSWORD_PROMPT);
+    let login_incorrect = expect::literal(LOGIN_INCORRECT);
+
+    session.send_line(&config.linux_user)?;
+    let mut result = session.expect(&[&prompt, &login_incorrect, &password_prompt], deadline)?;
+    if result.matched == Some(2) {
+        session.send_secret_line(&config.linux_password)?;
+        let after_password = session.expect(&[&prompt, &login_incorrect], deadline)?;
+        result.output.append(after_password.output);
+        result.matched = after_password.matched;
     }
-    console_output
+
+    if result.matched == Some(0) {
+        session.ignore(expect::regex(KERNEL_MESSAGE));
+    }
+    let stop = match result.matched {
+        Some(_) => Stop::Done,
+        None => Stop::Deadline,
+    };
+    Ok((result.output, stop))
+}
+
+fn run_linux_cmd(
+    session: &mut Expect,
+    cmd: &str,
+    timeout: Duration,
+) -> Result<(ConsoleOutput, Stop), Error> {
+    session.send_line(cmd)?;
+
+    let prompt = session.prompt().clone();
+    expect_output(session, &[&prompt, &reset_banner()], timeout)
+}
+
+/// Returns whether `console_output` contains the prompt of a Linux shell,
+/// e.g. `root@gateway:~# `.
+fn at_shell_prompt(console_output: &str) -> bool {
+    expect::regex(LINUX_PROMPT).is_match(console_output)
+}
+
+/// Returns a pattern matching the line of the SPL banner printed after a
+/// reset, the line is needed to tell the reset.
+fn reset_banner() -> Regex {
+    expect::regex(&format!(r"{}[^\n]*\n", regex::escape(SPL_BANNER)))
 }
 
 fn log_issue(issue: &str, instructions: &str) {
@@ -202,11 +643,179 @@ fn log_issue(issue: &str, instructions: &str) {
     info!("{instructions}");
 }
 
-fn run_u_boot_check(serial_port: &mut Box<dyn SerialPort>, info: &CheckInfo, lm_id: &str) -> bool {
-    let console_output = run_u_boot_cmd(serial_port, info.command.expect("Missing U-Boot command"), lm_id);
-
-    !(info
+/// Matches `check` against `console_output`.
+///
+/// If an expected string is missing, the whole output is used as excerpt when
+/// `whole_output_as_excerpt` is set, e.g. for the short output of a command.
+fn evaluate(
+    check: &Check,
+    console_output: &ConsoleOutput,
+    whole_output_as_excerpt: bool,
+    config: &Config,
+) -> CheckResult {
+    if let Some((line, _)) = check
         .not_expected
-        .is_some_and(|x| console_output.contains(x))
-        || info.expected.is_some_and(|x| !console_output.contains(x)))
+        .as_deref()
+        .and_then(|x| find(check, console_output, x))
+    {
+        return CheckResult::new(check, Outcome::Fail, Some(line));
+    }
+
+    let found = check
+        .expected
+        .as_deref()
+        .and_then(|x| find(check, console_output, x));
+    if check.expected.is_some() && found.is_none() {
+        let excerpt = whole_output_as_excerpt.then(|| console_output.text().trim().to_string());
+        return CheckResult::new(check, Outcome::Fail, excerpt);
+    }
+
+    // The output of the check's command is only decoded once it passed
+    if let Some(decoder) = check.decode {
+        return decode(check, decoder, console_output.text(), config);
+    }
+
+    let Some((line, values)) = found else {
+        return CheckResult::new(check, Outcome::Pass, None);
+    };
+
+    let accepted = check
+        .values
+        .iter()
+        .all(|(name, limits)| values.get(name).is_some_and(|value| limits.accepts(value)));
+    let outcome = if accepted {
+        Outcome::Pass
+    } else {
+        Outcome::Fail
+    };
+
+    CheckResult {
+        values,
+        ..CheckResult::new(check, outcome, Some(line))
+    }
+}
+
+/// Decodes the output of the check's command.
+///
+/// The check's message is only used if the output contains nothing to
+/// decode, otherwise failures are described by the decoder.
+fn decode(check: &Check, decoder: Decoder, console_output: &str, config: &Config) -> CheckResult {
+    let excerpt = Some(console_output.trim().to_string());
+    let fail = |message: String| CheckResult {
+        message,
+        ..CheckResult::new(check, Outcome::Fail, excerpt.clone())
+    };
+    // The decoded values are recorded whether they are valid or not
+    let judge = |validated: Result<(), String>, values: BTreeMap<String, String>| {
+        let result = match validated {
+            Ok(()) => CheckResult::new(check, Outcome::Pass, None),
+            Err(message) => fail(message),
+        };
+        CheckResult { values, ..result }
+    };
+
+    match decoder {
+        Decoder::FactoryData => {
+            let dump = factory_data::parse_dump(console_output);
+            if dump.is_empty() {
+                return CheckResult::new(check, Outcome::Fail, excerpt);
+            }
+            let data = match FactoryData::decode(&dump) {
+                Ok(data) => data,
+                Err(e) => return fail(e.to_string()),
+            };
+            judge(data.validate().map_err(|e| e.to_string()), data.values())
+        }
+        Decoder::BadBlocks => {
+            let Some(bad_blocks) = BadBlocks::parse(console_output) else {
+                return CheckResult::new(check, Outcome::Fail, excerpt);
+            };
+            judge(
+                bad_blocks
+                    .validate(config.max_bad_blocks)
+                    .map_err(|e| e.to_string()),
+                bad_blocks.values(),
+            )
+        }
+        Decoder::Environment => {
+            let Some(env) = Environment::parse(console_output) else {
+                return CheckResult::new(check, Outcome::Fail, excerpt);
+            };
+            judge(
+                env.validate(&config.required_env())
+                    .map_err(|e| e.to_string()),
+                env.values(),
+            )
+        }
+        Decoder::RedundantEnv => {
+            let Some(flags) = RedundantFlags::parse(console_output) else {
+                return CheckResult::new(check, Outcome::Fail, excerpt);
+            };
+            judge(flags.validate().map_err(|e| e.to_string()), flags.values())
+        }
+        Decoder::NorFlash => {
+            // The banner is decoded, only the line of the flash is of interest
+            let excerpt = console_output
+                .lines()
+                .find(|line| line.contains(nor::DETECTED))
+                .map(|line| line.trim().to_string());
+            let Some(flash) = NorFlash::parse(console_output) else {
+                return CheckResult::new(check, Outcome::Fail, excerpt);
+            };
+            let result = match flash.validate(&config.qualified_nor_flashes()) {
+                Ok(()) => CheckResult::new(check, Outcome::Pass, None),
+                Err(e) => CheckResult {
+                    message: e.to_string(),
+                    ..CheckResult::new(check, Outcome::Fail, excerpt)
+                },
+            };
+            CheckResult {
+                values: flash.values(),
+                ..result
+            }
+        }
+        Decoder::MtdLayout => {
+            let devices = mtd::parse_list(console_output);
+            if devices.is_empty() {
+                return CheckResult::new(check, Outcome::Fail, excerpt);
+            }
+            let deviations: Vec<String> = mtd::compare(&devices, &config.mtd_layout())
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            let result = if deviations.is_empty() {
+                CheckResult::new(check, Outcome::Pass, None)
+            } else {
+                fail(format!("Wrong MTD layout: {}", deviations.join(", ")))
+            };
+            CheckResult {
+                values: mtd::values(&devices),
+                ..result
+            }
+        }
+    }
+}
+
+/// Searches the lines of `console_output` for `pattern`, which is a regular
+/// expression if the check says so.
+///
+/// Returns the matching line and the values of named capture groups.
+fn find(
+    check: &Check,
+    console_output: &ConsoleOutput,
+    pattern: &str,
+) -> Option<(String, BTreeMap<String, String>)> {
+    let matcher = LineMatcher::new(pattern, check.regex).expect("Invalid regex in check catalog");
+    let found = matcher.find(console_output.lines())?;
+
+    Some((found.line.text.trim().to_string(), found.values))
+}
+
+/// Returns the last non-empty line of `console_output`, i.e. where it got stuck.
+fn last_line(console_output: &str) -> Option<String> {
+    console_output
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map(String::from)
 }
@@ -0,0 +1,141 @@
+//! Virtual gateway on a pseudo-terminal, to develop and demo without a jig.
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Simulated GARDENA smart Gateway on a pseudo-terminal"
+)]
+struct Args {
+    /// Scenario to play, e.g. `tests/data/no_nand.toml`
+    scenario: PathBuf,
+    /// Create a symlink to the pseudo-terminal, e.g. to keep the configured
+    /// serial port of the doctor
+    #[arg(long)]
+    link: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn run(_args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The simulator requires pseudo-terminals, which are only available on Unix".into())
+}
+
+#[cfg(unix)]
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    use nix::pty::{openpty, OpenptyResult};
+    use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+    use nix::unistd::ttyname;
+    use smart_garden_gateway_doctor::simulator::{Scenario, Simulator, State};
+    use std::fs::File;
+    use std::time::Instant;
+
+    let scenario = Scenario::load(&args.scenario)?;
+
+    // The slave stays open, otherwise reading from the master fails while
+    // no client is connected.
+    let OpenptyResult { master, slave } = openpty(None, None)?;
+    let mut termios = tcgetattr(&slave)?;
+    cfmakeraw(&mut termios);
+    tcsetattr(&slave, SetArg::TCSANOW, &termios)?;
+
+    let path = ttyname(&slave)?;
+    if let Some(link) = &args.link {
+        if link.is_symlink() {
+            std::fs::remove_file(link)?;
+        }
+        std::os::unix::fs::symlink(&path, link)?;
+    }
+    println!(
+        "Simulating {} on {}",
+        args.scenario.display(),
+        path.display()
+    );
+
+    let mut master = File::from(master);
+    let mut simulator = Simulator::new(scenario);
+    let mut buf = [0; 256];
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let timeout = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        let len = pty::read(&mut master, &mut buf, timeout)?;
+
+        let state = simulator.state();
+        let output = if len == 0 {
+            simulator.expire()
+        } else {
+            simulator.input(&buf[..len])
+        };
+        // Input does not delay the next reset of a unit in a boot loop.
+        if len == 0 || state != State::BootLoop {
+            deadline = simulator.timeout().map(|t| Instant::now() + t);
+        }
+        if simulator.state() != state {
+            println!("{:?}", simulator.state());
+        }
+
+        pty::write(&mut master, &output)?;
+    }
+}
+
+#[cfg(unix)]
+mod pty {
+    use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+    use std::fs::File;
+    use std::io::{self, Read, Write};
+    use std::os::fd::AsFd;
+    use std::time::Duration;
+
+    /// Time to print a line, roughly the speed of the real console.
+    const LINE_DELAY: Duration = Duration::from_millis(2);
+
+    /// Gap in milliseconds ending an input. The doctor's writes only return
+    /// once the simulator read them, so without waiting a command would be
+    /// split at arbitrary points unlike on a real UART.
+    const INPUT_GAP: u16 = 50;
+
+    /// Reads the available bytes into `buf`, waiting at most `timeout` or
+    /// forever if it is `None`.
+    ///
+    /// Returns 0 if nothing was received in time.
+    pub fn read(master: &mut File, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        let timeout = timeout.map_or(PollTimeout::NONE, |t| {
+            PollTimeout::try_from(t).unwrap_or(PollTimeout::MAX)
+        });
+        if !readable(master, timeout)? {
+            return Ok(0);
+        }
+        let mut len = master.read(buf)?;
+        while len < buf.len() && readable(master, PollTimeout::from(INPUT_GAP))? {
+            len += master.read(&mut buf[len..])?;
+        }
+        Ok(len)
+    }
+
+    fn readable(master: &File, timeout: PollTimeout) -> io::Result<bool> {
+        let mut fds = [PollFd::new(master.as_fd(), PollFlags::POLLIN)];
+        Ok(poll(&mut fds, timeout)? > 0)
+    }
+
+    pub fn write(master: &mut File, output: &str) -> io::Result<()> {
+        for line in output.split_inclusive('\n') {
+            master.write_all(line.as_bytes())?;
+            std::thread::sleep(LINE_DELAY);
+        }
+        Ok(())
+    }
+}
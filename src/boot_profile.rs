@@ -0,0 +1,86 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Hardware and firmware details reported by the SPL and U-Boot banners.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BootProfile {
+    pub spl_version: Option<String>,
+    pub u_boot_version: Option<String>,
+    pub u_boot_build_date: Option<String>,
+    pub cpu: Option<String>,
+    pub boot_mode: Option<String>,
+    pub cpu_clock_mhz: Option<u32>,
+    pub bus_clock_mhz: Option<u32>,
+    pub xtal_mhz: Option<u32>,
+    pub model: Option<String>,
+    pub ram_size: Option<String>,
+    pub nor_flash: Option<String>,
+}
+
+impl BootProfile {
+    /// Extracts the boot profile from the console output captured while
+    /// entering U-Boot. Fields missing in the output are left empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if something unexpected happens.
+    #[must_use]
+    pub fn parse(console_output: &str) -> BootProfile {
+        let spl = capture(
+            console_output,
+            r"U-Boot SPL (?P<version>\S+) \((?P<date>[^)]+)\)",
+        );
+        let u_boot = capture(
+            console_output,
+            r"U-Boot (?P<version>\d{4}\.\d{2}\S*) \((?P<date>[^)]+)\)",
+        );
+        let clock = capture(
+            console_output,
+            r"Clock:\s+CPU:\s+(?P<cpu>\d+)MHz, Bus:\s+(?P<bus>\d+)MHz, XTAL:\s+(?P<xtal>\d+)MHz",
+        );
+        let mhz = |name| clock.get(name).and_then(|s| s.parse().ok());
+
+        BootProfile {
+            spl_version: spl.get("version").cloned(),
+            u_boot_version: u_boot.get("version").cloned(),
+            u_boot_build_date: u_boot.get("date").cloned(),
+            cpu: line_value(console_output, "CPU:"),
+            boot_mode: line_value(console_output, "Boot:"),
+            cpu_clock_mhz: mhz("cpu"),
+            bus_clock_mhz: mhz("bus"),
+            xtal_mhz: mhz("xtal"),
+            model: line_value(console_output, "Model:"),
+            ram_size: line_value(console_output, "DRAM:"),
+            nor_flash: capture(console_output, r"SF: Detected (?P<chip>\S+)")
+                .get("chip")
+                .cloned(),
+        }
+    }
+}
+
+/// Returns the named groups of the first match of `pattern`.
+fn capture(console_output: &str, pattern: &str) -> HashMap<String, String> {
+    let re = Regex::new(pattern).expect("Failed to create regular expression");
+    let Some(captures) = re.captures(console_output) else {
+        return HashMap::new();
+    };
+
+    re.capture_names()
+        .flatten()
+        .filter_map(|name| {
+            captures
+                .name(name)
+                .map(|m| (name.to_string(), m.as_str().to_string()))
+        })
+        .collect()
+}
+
+/// Returns the rest of the first line starting with `label`.
+fn line_value(console_output: &str, label: &str) -> Option<String> {
+    console_output
+        .lines()
+        .find_map(|line| line.trim_start().strip_prefix(label))
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
@@ -0,0 +1,161 @@
+use crate::analyzer::{Direction, TranscriptEntry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Command leaving U-Boot, the kernel milestones are timed from it.
+pub static BOOT_COMMAND: &str = "boot";
+
+/// Point in the boot of the DUT recognized in its console output.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Milestone {
+    FirstByte,
+    Spl,
+    UBoot,
+    Dram,
+    Prompt,
+    KernelStart,
+    LinuxVersion,
+    Login,
+}
+
+impl Milestone {
+    const ALL: [Milestone; 8] = [
+        Milestone::FirstByte,
+        Milestone::Spl,
+        Milestone::UBoot,
+        Milestone::Dram,
+        Milestone::Prompt,
+        Milestone::KernelStart,
+        Milestone::LinuxVersion,
+        Milestone::Login,
+    ];
+
+    /// Returns the name used in the config and the report.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Milestone::FirstByte => "first-byte",
+            Milestone::Spl => "spl",
+            Milestone::UBoot => "u-boot",
+            Milestone::Dram => "dram",
+            Milestone::Prompt => "prompt",
+            Milestone::KernelStart => "kernel-start",
+            Milestone::LinuxVersion => "linux-version",
+            Milestone::Login => "login",
+        }
+    }
+
+    /// Returns whether `console_output` shows that the milestone was reached.
+    fn reached(self, console_output: &str) -> bool {
+        match self {
+            Milestone::FirstByte => !console_output.is_empty(),
+            Milestone::Spl => console_output.contains("U-Boot SPL "),
+            Milestone::UBoot => console_output
+                .lines()
+                .any(|line| line.starts_with("U-Boot ") && !line.starts_with("U-Boot SPL ")),
+            Milestone::Dram => console_output.contains("DRAM:"),
+            Milestone::Prompt => console_output.contains("=>"),
+            Milestone::KernelStart => console_output.contains("Starting kernel"),
+            Milestone::LinuxVersion => console_output.contains("Linux version"),
+            Milestone::Login => console_output.contains("login:"),
+        }
+    }
+
+    /// Returns whether the milestone is timed from the boot command instead
+    /// of the power on.
+    fn after_boot_command(self) -> bool {
+        self >= Milestone::KernelStart
+    }
+}
+
+impl fmt::Display for Milestone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Milestone::FirstByte => write!(f, "First output"),
+            Milestone::Spl => write!(f, "SPL"),
+            Milestone::UBoot => write!(f, "U-Boot"),
+            Milestone::Dram => write!(f, "DRAM"),
+            Milestone::Prompt => write!(f, "U-Boot prompt"),
+            Milestone::KernelStart => write!(f, "Kernel start"),
+            Milestone::LinuxVersion => write!(f, "Linux version"),
+            Milestone::Login => write!(f, "Login prompt"),
+        }
+    }
+}
+
+/// Milestone which was reached later than allowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SlowMilestone {
+    pub milestone: Milestone,
+    pub elapsed_ms: u64,
+    pub limit_ms: u64,
+}
+
+/// Time it took the DUT to reach each milestone, in milliseconds since the
+/// power on or, for the kernel milestones, since the boot command was sent.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BootTiming {
+    pub milestones: BTreeMap<Milestone, u64>,
+}
+
+impl BootTiming {
+    /// Finds the milestones in `transcript`, the DUT was powered on at
+    /// `powered_on`. Only the kernel milestones are measured if the time of
+    /// the power on is unknown, e.g. if it is switched externally.
+    #[must_use]
+    pub fn measure(
+        powered_on: Option<DateTime<Utc>>,
+        transcript: &[TranscriptEntry],
+    ) -> BootTiming {
+        let mut milestones = BTreeMap::new();
+        let mut start = powered_on;
+        let mut booted = false;
+        let mut received = String::new();
+
+        for entry in transcript {
+            if entry.direction == Direction::Tx {
+                if entry.text.trim_end() == BOOT_COMMAND && !booted {
+                    start = Some(entry.time);
+                    booted = true;
+                    received.clear();
+                }
+                continue;
+            }
+
+            received += &entry.text;
+            let Some(start) = start else {
+                continue;
+            };
+            let elapsed_ms = u64::try_from((entry.time - start).num_milliseconds()).unwrap_or(0);
+            for milestone in Milestone::ALL {
+                if milestone.after_boot_command() == booted
+                    && !milestones.contains_key(&milestone)
+                    && milestone.reached(&received)
+                {
+                    milestones.insert(milestone, elapsed_ms);
+                }
+            }
+        }
+
+        BootTiming { milestones }
+    }
+
+    /// Returns the milestones reached later than their limit in `limits`.
+    #[must_use]
+    pub fn slow(&self, limits: &BTreeMap<Milestone, u64>) -> Vec<SlowMilestone> {
+        self.milestones
+            .iter()
+            .filter_map(|(&milestone, &elapsed_ms)| {
+                let limit_ms = *limits.get(&milestone)?;
+                (elapsed_ms > limit_ms).then_some(SlowMilestone {
+                    milestone,
+                    elapsed_ms,
+                    limit_ms,
+                })
+            })
+            .collect()
+    }
+}
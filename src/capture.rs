@@ -0,0 +1,278 @@
+use crate::analyzer::Direction;
+use crate::catalog::{self, Catalog};
+use crate::config::Config;
+use crate::expect::REDACTED;
+use crate::transport::ConsoleTransport;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Capture format version written by this release.
+pub const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Parse(toml::de::Error),
+    UnsupportedVersion(u32),
+    Catalog(catalog::Error),
+    Serialize(toml::ser::Error),
+    Write(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Read(e) => write!(f, "Failed to read capture: {e}"),
+            Error::Parse(e) => write!(f, "Failed to parse capture: {e}"),
+            Error::UnsupportedVersion(v) => write!(
+                f,
+                "Unsupported capture version {v}, this release supports version {VERSION}"
+            ),
+            Error::Catalog(e) => write!(f, "Invalid catalog in capture: {e}"),
+            Error::Serialize(e) => write!(f, "Failed to serialize capture: {e}"),
+            Error::Write(e) => write!(f, "Failed to write capture: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Bytes sent or received, kept as text if they are valid UTF-8.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Data {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Data {
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Data::Text(text) => text.as_bytes(),
+            Data::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+impl From<&[u8]> for Data {
+    fn from(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Data::Text(String::from(text)),
+            Err(_) => Data::Bytes(bytes.to_vec()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Event {
+    /// Milliseconds since the start of the capture.
+    pub time_ms: u64,
+    pub direction: Direction,
+    pub data: Data,
+}
+
+/// Console session as seen by the doctor, with the chunk boundaries of every
+/// read and write.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Capture {
+    pub version: u32,
+    pub started: DateTime<Utc>,
+    /// Diagnosis of the recorded session, expected again when replaying.
+    #[serde(default)]
+    pub message: String,
+    /// Catalog and config the session was recorded with, the session is
+    /// replayed with them as the doctor's commands depend on both.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catalog: Option<Catalog>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Config>,
+    #[serde(default, rename = "event")]
+    pub events: Vec<Event>,
+}
+
+impl Default for Capture {
+    fn default() -> Capture {
+        Capture {
+            version: VERSION,
+            started: Utc::now(),
+            message: String::new(),
+            catalog: None,
+            config: None,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Capture {
+    /// # Errors
+    ///
+    /// Will return `Err` if the capture cannot be read, is malformed, of an
+    /// unsupported version or holds an invalid catalog.
+    pub fn load(path: &Path) -> Result<Capture, Error> {
+        let content = std::fs::read_to_string(path).map_err(Error::Read)?;
+        let capture: Capture = toml::from_str(&content).map_err(Error::Parse)?;
+        if capture.version != VERSION {
+            return Err(Error::UnsupportedVersion(capture.version));
+        }
+        if let Some(catalog) = &capture.catalog {
+            catalog.validate().map_err(Error::Catalog)?;
+        }
+        Ok(capture)
+    }
+
+    /// Keeps `catalog` and `config` with the session, the Linux password is
+    /// removed from both the config and the session.
+    pub fn set_setup(&mut self, catalog: &Catalog, config: &Config) {
+        if !config.linux_password.is_empty() {
+            let password_line = format!("{}\n", config.linux_password);
+            for event in &mut self.events {
+                if event.direction == Direction::Tx
+                    && event.data.as_bytes() == password_line.as_bytes()
+                {
+                    event.data = Data::Text(String::from(REDACTED));
+                }
+            }
+        }
+        self.catalog = Some(catalog.clone());
+        self.config = Some(Config {
+            linux_password: String::new(),
+            ..config.clone()
+        });
+    }
+
+    /// Returns the catalog and config to replay the session with, the
+    /// defaults if the capture has none.
+    #[must_use]
+    pub fn setup(&self) -> (Catalog, Config) {
+        (
+            self.catalog.clone().unwrap_or_default(),
+            self.config.clone().unwrap_or_default(),
+        )
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the capture cannot be serialized or written.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let content = toml::to_string(self).map_err(Error::Serialize)?;
+        std::fs::write(path, content).map_err(Error::Write)
+    }
+}
+
+/// Transport recording everything passing through `console`.
+pub struct RecordingTransport<'a> {
+    console: &'a mut dyn ConsoleTransport,
+    start: Instant,
+    capture: Capture,
+}
+
+impl<'a> RecordingTransport<'a> {
+    pub fn new(console: &'a mut dyn ConsoleTransport) -> RecordingTransport<'a> {
+        RecordingTransport {
+            console,
+            start: Instant::now(),
+            capture: Capture::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn into_capture(self) -> Capture {
+        self.capture
+    }
+
+    fn record(&mut self, direction: Direction, data: &[u8]) {
+        let time_ms = u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        self.capture.events.push(Event {
+            time_ms,
+            direction,
+            data: Data::from(data),
+        });
+    }
+}
+
+impl ConsoleTransport for RecordingTransport<'_> {
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        let len = self.console.read(buf, timeout)?;
+        if len > 0 {
+            self.record(Direction::Rx, &buf[..len]);
+        }
+        Ok(len)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.console.write_all(buf)?;
+        self.record(Direction::Tx, buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.console.flush()
+    }
+
+    fn set_power_line(&mut self, level: bool) -> io::Result<()> {
+        self.console.set_power_line(level)
+    }
+}
+
+/// Transport playing back the received data of a [`Capture`].
+///
+/// Data received after the doctor sent something is held back until the
+/// doctor sends again, so replies are not played before the command.
+pub struct ReplayTransport {
+    events: VecDeque<Event>,
+}
+
+impl ReplayTransport {
+    #[must_use]
+    pub fn new(capture: Capture) -> ReplayTransport {
+        ReplayTransport {
+            events: capture.events.into(),
+        }
+    }
+}
+
+impl ConsoleTransport for ReplayTransport {
+    fn read(&mut self, buf: &mut [u8], _timeout: Duration) -> io::Result<usize> {
+        let Some(event) = self.events.front_mut() else {
+            return Ok(0);
+        };
+        if event.direction != Direction::Rx {
+            return Ok(0);
+        }
+
+        let bytes = event.data.as_bytes();
+        let len = bytes.len().min(buf.len());
+        buf[..len].copy_from_slice(&bytes[..len]);
+        if len < bytes.len() {
+            event.data = Data::Bytes(bytes[len..].to_vec());
+        } else {
+            self.events.pop_front();
+        }
+        Ok(len)
+    }
+
+    fn write_all(&mut self, _buf: &[u8]) -> io::Result<()> {
+        while self
+            .events
+            .front()
+            .is_some_and(|e| e.direction == Direction::Tx)
+        {
+            self.events.pop_front();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_power_line(&mut self, _level: bool) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+use figment::{
+    providers::{Format, Toml},
+    Figment,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+/// Catalog format version understood by this release.
+pub const VERSION: u32 = 1;
+
+static DEFAULT_CATALOG: &str = include_str!("../catalog/default.toml");
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// Matched against the console output captured while entering U-Boot.
+    Early,
+    /// Matched against the output of `command` run in the U-Boot shell.
+    UBoot,
+    /// Like U-Boot checks, but only run if the network test is enabled.
+    Network,
+    /// Matched against the kernel log after booting Linux, if enabled.
+    Kernel,
+    /// Matched against the output of `command` run in the Linux shell, or
+    /// against the login if there is no command.
+    Linux,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Early => write!(f, "early"),
+            Phase::UBoot => write!(f, "u-boot"),
+            Phase::Network => write!(f, "network"),
+            Phase::Kernel => write!(f, "kernel"),
+            Phase::Linux => write!(f, "linux"),
+        }
+    }
+}
+
+/// Decoder for console output which cannot be checked by matching patterns.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Decoder {
+    /// `md.b` dump of the factory data, see [`crate::factory_data`].
+    FactoryData,
+    /// Bad block list of `mtd bad`, see [`crate::nand`].
+    BadBlocks,
+    /// Devices and partitions of `mtd list`, see [`crate::mtd`].
+    MtdLayout,
+    /// SPI-NOR flash detected by U-Boot, see [`crate::nor`].
+    NorFlash,
+    /// Variables listed by `printenv`, see [`crate::environment`].
+    Environment,
+    /// `md.b` dump of the headers of both copies of the U-Boot environment,
+    /// see [`crate::environment::RedundantFlags`].
+    RedundantEnv,
+}
+
+/// Limits for a value captured by a named group of a check's `expected` regex.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ValueLimits {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub allowed: Option<Vec<String>>,
+}
+
+impl ValueLimits {
+    #[must_use]
+    pub fn accepts(&self, value: &str) -> bool {
+        if let Some(allowed) = &self.allowed {
+            if !allowed.iter().any(|a| a == value) {
+                return false;
+            }
+        }
+
+        if self.min.is_none() && self.max.is_none() {
+            return true;
+        }
+
+        value.parse::<f64>().is_ok_and(|v| {
+            self.min.is_none_or(|min| v >= min) && self.max.is_none_or(|max| v <= max)
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Check {
+    pub id: String,
+    pub phase: Phase,
+    pub command: Option<String>,
+    pub expected: Option<String>,
+    pub not_expected: Option<String>,
+    /// Treat `expected` and `not_expected` as regular expressions.
+    #[serde(default)]
+    pub regex: bool,
+    /// Limits for the named captures of `expected`, keyed by group name.
+    #[serde(default)]
+    pub values: BTreeMap<String, ValueLimits>,
+    pub message: String,
+    pub instructions: String,
+    /// Seconds to wait for the output of `command` instead of the configured
+    /// `command_timeout`.
+    pub timeout: Option<u64>,
+    /// Decodes the output of `command` instead of matching `expected`.
+    pub decode: Option<Decoder>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Catalog {
+    pub version: u32,
+    #[serde(rename = "check")]
+    pub checks: Vec<Check>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(Box<figment::Error>),
+    UnsupportedVersion(u32),
+    DuplicateId(String),
+    MissingPattern(String),
+    MissingCommand(String),
+    UnexpectedCommand(String),
+    UnexpectedDecoder(String),
+    InvalidRegex(String, regex::Error),
+    UnknownCapture(String, String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "Failed to parse check catalog: {e}"),
+            Error::UnsupportedVersion(v) => write!(
+                f,
+                "Unsupported check catalog version {v} (expected {VERSION})"
+            ),
+            Error::DuplicateId(id) => write!(f, "Check \"{id}\" is defined more than once"),
+            Error::MissingPattern(id) => write!(
+                f,
+                "Check \"{id}\" has neither \"expected\", \"not_expected\" nor \"decode\""
+            ),
+            Error::MissingCommand(id) => write!(
+                f,
+                "Check \"{id}\" has no \"command\", U-Boot and network checks run one"
+            ),
+            Error::UnexpectedCommand(id) => write!(
+                f,
+                "Check \"{id}\" must not have a \"command\", early and kernel checks run none"
+            ),
+            Error::UnexpectedDecoder(id) => write!(
+                f,
+                "Check \"{id}\" must not have a \"decode\", only early and U-Boot checks decode their output"
+            ),
+            Error::InvalidRegex(id, e) => write!(f, "Check \"{id}\" has an invalid regex: {e}"),
+            Error::UnknownCapture(id, name) => write!(
+                f,
+                "Check \"{id}\" has limits for \"{name}\", which is not captured by \"expected\""
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<figment::Error> for Error {
+    fn from(e: figment::Error) -> Self {
+        Error::Parse(Box::new(e))
+    }
+}
+
+impl Default for Catalog {
+    /// Returns the built-in catalog.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the built-in catalog is invalid.
+    fn default() -> Catalog {
+        Catalog::from_toml(DEFAULT_CATALOG).expect("Built-in check catalog is invalid")
+    }
+}
+
+impl Catalog {
+    /// Loads the catalog from `path`, or the built-in catalog if no path is given.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the catalog cannot be read or is malformed.
+    pub fn load(path: Option<&Path>) -> Result<Catalog, Error> {
+        match path {
+            Some(path) => Catalog::from_figment(&Figment::from(Toml::file(path))),
+            None => Ok(Catalog::default()),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the catalog is malformed.
+    pub fn from_toml(toml: &str) -> Result<Catalog, Error> {
+        Catalog::from_figment(&Figment::from(Toml::string(toml)))
+    }
+
+    fn from_figment(figment: &Figment) -> Result<Catalog, Error> {
+        let catalog: Catalog = figment.extract()?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` describing the first problem found in the catalog.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.version != VERSION {
+            return Err(Error::UnsupportedVersion(self.version));
+        }
+
+        let mut ids = HashSet::new();
+        for check in &self.checks {
+            if !ids.insert(check.id.as_str()) {
+                return Err(Error::DuplicateId(check.id.clone()));
+            }
+            if check.expected.is_none() && check.not_expected.is_none() && check.decode.is_none() {
+                return Err(Error::MissingPattern(check.id.clone()));
+            }
+            if check.decode.is_some() && matches!(check.phase, Phase::Kernel | Phase::Linux) {
+                return Err(Error::UnexpectedDecoder(check.id.clone()));
+            }
+            match (check.phase, &check.command) {
+                (Phase::Early | Phase::Kernel, Some(_)) => {
+                    return Err(Error::UnexpectedCommand(check.id.clone()))
+                }
+                (Phase::UBoot | Phase::Network, None) => {
+                    return Err(Error::MissingCommand(check.id.clone()))
+                }
+                _ => {}
+            }
+            check.validate_values()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the checks of `phase` in catalog order.
+    pub fn phase(&self, phase: Phase) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(move |c| c.phase == phase)
+    }
+}
+
+impl Check {
+    fn validate_values(&self) -> Result<(), Error> {
+        let compile =
+            |pattern| Regex::new(pattern).map_err(|e| Error::InvalidRegex(self.id.clone(), e));
+        let mut names = HashSet::new();
+
+        if self.regex {
+            if let Some(pattern) = &self.not_expected {
+                compile(pattern)?;
+            }
+            if let Some(pattern) = &self.expected {
+                names.extend(
+                    compile(pattern)?
+                        .capture_names()
+                        .flatten()
+                        .map(String::from),
+                );
+            }
+        }
+
+        match self.values.keys().find(|name| !names.contains(*name)) {
+            Some(name) => Err(Error::UnknownCapture(self.id.clone(), name.clone())),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use clap::{Subcommand, ValueEnum};
+use serde::Serialize;
+use smart_garden_gateway_doctor::analyzer::{diagnose, Diagnosis, Outcome};
+use smart_garden_gateway_doctor::capture::RecordingTransport;
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::history::History;
+use smart_garden_gateway_doctor::iprid;
+use smart_garden_gateway_doctor::jig::open_console;
+use smart_garden_gateway_doctor::report::Report;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const EXIT_FAULTY: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_STATION_FAULT: u8 = 3;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Diagnose a single unit without the GUI
+    Diagnose(DiagnoseArgs),
+}
+
+#[derive(clap::Args)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct DiagnoseArgs {
+    /// Serial port of the jig or tcp://<host>:<port> of a console server
+    /// [default: configured serial port]
+    #[arg(long)]
+    port: Option<String>,
+    /// IPRID of the unit on the jig
+    #[arg(long)]
+    iprid: String,
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Evaluate all checks instead of stopping at the first failure
+    #[arg(long)]
+    exhaustive: bool,
+    /// Leave switching the unit's power to the operator or an external
+    /// switch, e.g. for console servers and the simulator
+    #[arg(long)]
+    no_power_control: bool,
+    /// Ping the station from U-Boot to test the Ethernet link
+    #[arg(long)]
+    network: bool,
+    /// Boot Linux after the U-Boot checks and check the kernel log
+    #[arg(long)]
+    linux: bool,
+    /// Record the console session next to the report, e.g. to add it to
+    /// the regression tests
+    #[arg(long)]
+    record: bool,
+    /// Check catalog to use [default: configured or built-in catalog]
+    #[arg(long)]
+    catalog: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Output<'a> {
+    iprid: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    station_fault: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    diagnosis: Option<&'a Diagnosis>,
+}
+
+/// Runs `command` and returns the exit code: 0 if the unit is healthy,
+/// 1 if it is faulty, 2 on invalid arguments and 3 on a station fault.
+pub fn run(command: Command) -> ExitCode {
+    match command {
+        Command::Diagnose(args) => run_diagnose(&args),
+    }
+}
+
+fn run_diagnose(args: &DiagnoseArgs) -> ExitCode {
+    if !iprid::is_valid(&args.iprid) {
+        print_usage_error(args, "Invalid IPRID entered");
+        return ExitCode::from(EXIT_USAGE);
+    }
+
+    let mut config = match Config::new() {
+        Ok(config) => config,
+        Err(e) => {
+            print(args, None, Some(&e.to_string()));
+            return ExitCode::from(EXIT_STATION_FAULT);
+        }
+    };
+    config.exhaustive |= args.exhaustive;
+    config.power_control &= !args.no_power_control;
+    config.record |= args.record;
+    config.network |= args.network;
+    config.linux |= args.linux;
+    if args.catalog.is_some() {
+        config.catalog.clone_from(&args.catalog);
+    }
+    if let Some(port) = &args.port {
+        config.serial_port.clone_from(port);
+    }
+
+    let result = config
+        .load_catalog()
+        .map_err(|e| e.to_string())
+        .and_then(|catalog| {
+            let port = &config.serial_port;
+            let mut console = open_console(port).map_err(|e| format!("{e} ({port})"))?;
+            let mut recorder = RecordingTransport::new(console.as_mut());
+            let mut diagnosis =
+                diagnose(&mut recorder, &catalog, &config).map_err(|e| e.to_string())?;
+            diagnosis.verify_iprid(&args.iprid);
+
+            let report = Report::new(&args.iprid, &config, &catalog, &diagnosis);
+            match report.save(&config.report_dir) {
+                Ok(path) => {
+                    eprintln!("Report written to {}", path.display());
+                    if config.record {
+                        let mut capture = recorder.into_capture();
+                        capture.message.clone_from(&diagnosis.message);
+                        capture.set_setup(&catalog, &config);
+                        let path = path.with_extension("capture.toml");
+                        match capture.save(&path) {
+                            Ok(()) => eprintln!("Capture written to {}", path.display()),
+                            Err(e) => eprintln!("{e}"),
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Failed to write report: {e}"),
+            }
+            if let Err(e) = History::open(&config.history_db).and_then(|h| h.record(&report)) {
+                eprintln!("Failed to record diagnosis in history: {e}");
+            }
+
+            Ok(diagnosis)
+        });
+
+    let (diagnosis, station_fault) = match result {
+        Ok(diagnosis) => (Some(diagnosis), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    print(args, diagnosis.as_ref(), station_fault.as_deref());
+
+    match diagnosis {
+        Some(d) if d.healthy => ExitCode::SUCCESS,
+        Some(_) => ExitCode::from(EXIT_FAULTY),
+        None => ExitCode::from(EXIT_STATION_FAULT),
+    }
+}
+
+fn print(args: &DiagnoseArgs, diagnosis: Option<&Diagnosis>, station_fault: Option<&str>) {
+    match args.format {
+        Format::Text => print_text(diagnosis, station_fault),
+        Format::Json => print_json(&Output {
+            iprid: &args.iprid,
+            error: None,
+            station_fault: station_fault.map(String::from),
+            diagnosis,
+        }),
+    }
+}
+
+/// Prints an error in the invocation itself, as a JSON object if requested so
+/// that scripts always get something to parse.
+fn print_usage_error(args: &DiagnoseArgs, error: &str) {
+    match args.format {
+        Format::Text => eprintln!("{error}"),
+        Format::Json => print_json(&Output {
+            iprid: &args.iprid,
+            error: Some(error),
+            station_fault: None,
+            diagnosis: None,
+        }),
+    }
+}
+
+fn print_json(output: &Output) {
+    match serde_json::to_string_pretty(output) {
+        Ok(json) => println!("{json}"),
+        Err(e) => eprintln!("Failed to serialize diagnosis: {e}"),
+    }
+}
+
+fn print_text(diagnosis: Option<&Diagnosis>, station_fault: Option<&str>) {
+    if let Some(station_fault) = station_fault {
+        println!("Station fault: {station_fault}");
+    }
+
+    let Some(diagnosis) = diagnosis else {
+        return;
+    };
+
+    println!("Issue: {}", diagnosis.message);
+    if let Some(instructions) = &diagnosis.instructions {
+        println!("Instructions: {instructions}");
+    }
+    for result in &diagnosis.results {
+        let outcome = match result.outcome {
+            Outcome::Pass => "pass",
+            Outcome::Fail => "FAIL",
+            Outcome::Skipped => "skip",
+        };
+        println!("  [{outcome}] {}", result.id);
+    }
+    if !diagnosis.boot_timing.milestones.is_empty() {
+        println!("Boot timing:");
+        for (milestone, elapsed_ms) in &diagnosis.boot_timing.milestones {
+            println!("  {milestone}: {elapsed_ms} ms");
+        }
+    }
+}
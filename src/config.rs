@@ -1,64 +1,251 @@
+use crate::boot_timing::Milestone;
+use crate::catalog::{self, Catalog};
+use crate::environment::ValuePattern;
+use crate::mtd::{Layout, Range};
+use crate::nor::Geometry;
 use figment::{
     providers::{Format, Serialized, Toml},
     Figment,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Debug)]
+pub enum Error {
+    NoConfigDir,
+    Load(Box<figment::Error>),
+    Serialize(toml::ser::Error),
+    Write(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoConfigDir => write!(f, "Failed to find the config directory of the user"),
+            Error::Load(e) => write!(f, "Failed to load config: {e}"),
+            Error::Serialize(e) => write!(f, "Failed to serialize config: {e}"),
+            Error::Write(e) => write!(f, "Failed to write config: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<figment::Error> for Error {
+    fn from(e: figment::Error) -> Self {
+        Error::Load(Box::new(e))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Config {
     pub serial_port: String,
+    /// Switch the DUT power with the RTS line of the serial port, otherwise
+    /// it is switched externally, e.g. for console servers or the simulator.
+    pub power_control: bool,
     pub invert_rts: bool,
+    /// Check catalog to use instead of the built-in one.
+    pub catalog: Option<PathBuf>,
+    /// Evaluate all checks instead of stopping at the first failure.
+    pub exhaustive: bool,
+    /// Name of the repair station, recorded in the reports.
+    pub station: String,
+    /// Directory the per-unit reports are written to.
+    pub report_dir: PathBuf,
+    /// Database with the history of all diagnosed units.
+    pub history_db: PathBuf,
+    /// Record the console session into a capture file next to the report.
+    pub record: bool,
+    /// Seconds to wait for the U-Boot prompt after powering on the DUT.
+    pub u_boot_timeout: u64,
+    /// Seconds to wait for the output of a command, unless the check sets its
+    /// own timeout.
+    pub command_timeout: u64,
+    /// Milliseconds the DUT may take to reach each milestone, slower boots
+    /// are reported. None are set by default, they depend on the units and
+    /// the station and are to be measured.
+    pub boot_time_limits: BTreeMap<Milestone, u64>,
+    /// Bad blocks the NAND flash may have before it is reported as worn.
+    pub max_bad_blocks: usize,
+    /// Partitions the MTD devices must have, by device and partition name.
+    /// Replaces the layout of the gateway, see [`Config::mtd_layout`].
+    pub mtd_layout: Option<Layout>,
+    /// SPI-NOR flashes qualified for the gateway and their geometry, by
+    /// chip name. Replaces the built-in list, see
+    /// [`Config::qualified_nor_flashes`].
+    pub qualified_nor_flashes: Option<BTreeMap<String, Geometry>>,
+    /// U-Boot variables which must be set, with a regex their whole value
+    /// must match. Replaces the built-in variables, see
+    /// [`Config::required_env`].
+    pub required_env: Option<BTreeMap<String, ValuePattern>>,
+    /// Test the Ethernet link after the U-Boot checks by pinging the station.
+    pub network: bool,
+    /// IP address of the test host on the bench network, pinged by the DUT.
+    pub station_ip: String,
+    /// IP address the DUT uses on the bench network.
+    pub dut_ip: String,
+    /// Boot Linux after the U-Boot checks and check the kernel log.
+    pub linux: bool,
+    /// Seconds to wait for the login prompt after booting Linux.
+    pub linux_timeout: u64,
+    /// Seconds to wait for the shell prompt after sending the credentials.
+    pub login_timeout: u64,
+    /// Credentials to log in on the Linux console.
+    pub linux_user: String,
+    pub linux_password: String,
 }
 
 impl Default for Config {
     fn default() -> Config {
         Config {
             serial_port: String::new(),
+            power_control: true,
             invert_rts: true, // Elrad's jig requires an inverted DTR signal for switching DUT power
+            catalog: None,
+            exhaustive: false,
+            station: String::new(),
+            report_dir: PathBuf::from("reports"),
+            history_db: PathBuf::from("history.sqlite"),
+            record: false,
+            u_boot_timeout: 10,
+            command_timeout: 10,
+            boot_time_limits: BTreeMap::new(),
+            max_bad_blocks: 20,
+            mtd_layout: None,
+            qualified_nor_flashes: None,
+            required_env: None,
+            network: false,
+            station_ip: String::from("192.168.1.1"),
+            dut_ip: String::from("192.168.1.2"),
+            linux: false,
+            linux_timeout: 120,
+            login_timeout: 10,
+            linux_user: String::from("root"),
+            linux_password: String::new(),
         }
     }
 }
 
 impl Config {
-    /// # Panics
+    /// Loads the config file of the user, missing settings are taken from the
+    /// defaults.
     ///
-    /// Panics if something unexpected happens.
-    #[must_use]
-    pub fn new() -> Config {
-        Figment::from(Serialized::defaults(Config::default()))
-            .merge(Toml::file(Config::file_path()))
-            .extract()
-            .expect("Failed to construct Config")
+    /// # Errors
+    ///
+    /// Will return `Err` if the config directory of the user is unknown or the
+    /// config file is malformed.
+    pub fn new() -> Result<Config, Error> {
+        Config::load(&Config::file_path()?)
+    }
+
+    /// Loads the config file `path` like [`Config::new`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the config file is malformed.
+    pub fn load(path: &Path) -> Result<Config, Error> {
+        Ok(Figment::from(Serialized::defaults(Config::default()))
+            .merge(Toml::file(path))
+            .extract()?)
     }
 
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if something unexpected happens.
-    pub fn save(&self) {
-        let toml = toml::to_string(&self).expect("Failed to serialize config");
-        let config_file_path = Config::file_path();
-        std::fs::create_dir_all(
-            config_file_path
-                .parent()
-                .expect("Failed to create config directory"),
-        )
-        .expect("Failed to create config directory");
-        let mut f = File::create(config_file_path).expect("Failed to create config file");
-        f.write_all(toml.as_bytes())
-            .expect("Failed to write config");
+    /// Will return `Err` if the config directory of the user is unknown or the
+    /// config cannot be serialized or written.
+    pub fn save(&self) -> Result<(), Error> {
+        let toml = toml::to_string(&self).map_err(Error::Serialize)?;
+        let config_file_path = Config::file_path()?;
+        if let Some(dir) = config_file_path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::Write)?;
+        }
+        let mut f = File::create(config_file_path).map_err(Error::Write)?;
+        f.write_all(toml.as_bytes()).map_err(Error::Write)
     }
 
-    /// # Panics
+    /// Returns the configured MTD layout or the layout of the gateway.
     ///
-    /// Panics if something unexpected happens.
-    fn file_path() -> PathBuf {
-        dirs::config_dir()
-            .expect("Failed to get config dir")
+    /// A configured layout replaces the built-in one as a whole, as figment
+    /// would merge it with the defaults and a partition could not be dropped.
+    #[must_use]
+    pub fn mtd_layout(&self) -> Layout {
+        self.mtd_layout.clone().unwrap_or_else(|| {
+            Layout::from([
+                (
+                    String::from("nor0"),
+                    BTreeMap::from([
+                        partition("uboot", 0x0, 0xa_0000),
+                        partition("uboot_env0", 0xa_0000, 0x1_0000),
+                        partition("uboot_env1", 0xb_0000, 0x1_0000),
+                        partition("factory", 0xc_0000, 0x1_0000),
+                        partition("unused", 0xd_0000, 0x73_0000),
+                    ]),
+                ),
+                (
+                    String::from("spi-nand0"),
+                    BTreeMap::from([partition("nand", 0x0, 0x800_0000)]),
+                ),
+            ])
+        })
+    }
+
+    /// Returns the configured or the built-in qualified SPI-NOR flashes.
+    #[must_use]
+    pub fn qualified_nor_flashes(&self) -> BTreeMap<String, Geometry> {
+        self.qualified_nor_flashes.clone().unwrap_or_else(|| {
+            BTreeMap::from([(
+                String::from("XM25QH64C"),
+                Geometry {
+                    page_size: 256,
+                    erase_size: 0x1000,
+                    size: 0x80_0000,
+                },
+            )])
+        })
+    }
+
+    /// Returns the configured or the built-in required U-Boot variables.
+    #[must_use]
+    pub fn required_env(&self) -> BTreeMap<String, ValuePattern> {
+        self.required_env.clone().unwrap_or_else(|| {
+            BTreeMap::from([
+                variable("bootcmd", "run boot_slot"),
+                variable("bootlimit", r"[1-9]\d*"),
+                variable("ethaddr", "([0-9a-f]{2}:){5}[0-9a-f]{2}"),
+            ])
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the configured catalog cannot be read or is malformed.
+    pub fn load_catalog(&self) -> Result<Catalog, catalog::Error> {
+        Catalog::load(self.catalog.as_deref())
+    }
+
+    fn file_path() -> Result<PathBuf, Error> {
+        Ok(dirs::config_dir()
+            .ok_or(Error::NoConfigDir)?
             .join("Husqvarna")
             .join("smart-garden-gateway-doctor")
-            .join("config.toml")
+            .join("config.toml"))
     }
 }
+
+fn partition(name: &str, offset: u64, size: u64) -> (String, Range) {
+    (String::from(name), Range { offset, size })
+}
+
+/// # Panics
+///
+/// Panics if `pattern` is invalid, the built-in patterns are part of the code.
+fn variable(name: &str, pattern: &str) -> (String, ValuePattern) {
+    let pattern = ValuePattern::try_from(String::from(pattern)).expect("Invalid pattern");
+    (String::from(name), pattern)
+}
@@ -0,0 +1,264 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Line of console output without its line ending.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    /// When the first character of the line was received.
+    pub time: DateTime<Utc>,
+    pub text: String,
+}
+
+/// State of an ANSI escape sequence, which may be split across reads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Escape {
+    #[default]
+    None,
+    /// After `ESC`.
+    Start,
+    /// After `ESC [`, until the final byte.
+    Csi,
+}
+
+/// Console output of the DUT assembled from the raw bytes read from the
+/// console.
+///
+/// Characters and escape sequences split across reads are kept until they are
+/// complete. Escape sequences and all control characters except tab and
+/// newline are removed, as are bytes which are not valid UTF-8, e.g. garbage
+/// while the DUT resets.
+#[derive(Clone, Debug, Default)]
+pub struct ConsoleOutput {
+    text: String,
+    lines: Vec<Line>,
+    /// Whether the last line has not ended yet.
+    open_line: bool,
+    /// Start of a multi-byte character waiting for the rest.
+    incomplete: Vec<u8>,
+    escape: Escape,
+}
+
+impl ConsoleOutput {
+    /// Adds `bytes` received at `time` and returns the text they added.
+    pub fn push(&mut self, bytes: &[u8], time: DateTime<Utc>) -> String {
+        let mut pending = std::mem::take(&mut self.incomplete);
+        pending.extend_from_slice(bytes);
+        let mut added = String::new();
+        let mut rest = pending.as_slice();
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    self.push_str(s, time, &mut added);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, invalid) = rest.split_at(e.valid_up_to());
+                    self.push_str(
+                        std::str::from_utf8(valid).unwrap_or_default(),
+                        time,
+                        &mut added,
+                    );
+                    let Some(len) = e.error_len() else {
+                        self.incomplete = invalid.to_vec();
+                        break;
+                    };
+                    rest = &invalid[len..];
+                }
+            }
+        }
+
+        added
+    }
+
+    /// Returns the whole text received so far.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the lines received so far, the last one may not have ended
+    /// yet, e.g. a prompt.
+    #[must_use]
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Splits the output at the byte index `at` of the text and returns the
+    /// part after it, which continues where the output left off.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is not on a character boundary of the text.
+    #[must_use]
+    pub fn split_off(&mut self, at: usize) -> ConsoleOutput {
+        let text = self.text.split_off(at);
+        let mut lines = self.lines.split_off(self.text.matches('\n').count());
+        let line_start = self.text.rfind('\n').map_or(0, |i| i + 1);
+        if at > line_start {
+            // The line containing `at` is split between both parts
+            if let Some(line) = lines.first_mut() {
+                let tail = line.text.split_off(at - line_start);
+                self.lines.push(Line {
+                    time: line.time,
+                    text: std::mem::replace(&mut line.text, tail),
+                });
+            }
+        }
+        if text.is_empty() {
+            lines.clear();
+        }
+
+        let rest = ConsoleOutput {
+            open_line: self.open_line && !text.is_empty(),
+            text,
+            lines,
+            incomplete: std::mem::take(&mut self.incomplete),
+            escape: std::mem::take(&mut self.escape),
+        };
+        self.open_line = !self.text.is_empty() && !self.text.ends_with('\n');
+        rest
+    }
+
+    /// Appends `other`, which continues where the output left off.
+    pub fn append(&mut self, other: ConsoleOutput) {
+        if !other.text.is_empty() {
+            let mut lines = other.lines.into_iter();
+            if self.open_line {
+                if let (Some(last), Some(first)) = (self.lines.last_mut(), lines.next()) {
+                    last.text += &first.text;
+                }
+            }
+            self.lines.extend(lines);
+            self.text += &other.text;
+            self.open_line = other.open_line;
+        }
+        self.incomplete = other.incomplete;
+        self.escape = other.escape;
+    }
+
+    /// Removes the text in the byte range `start..end`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is not on character boundaries of the text.
+    pub fn remove(&mut self, start: usize, end: usize) {
+        let mut removed = self.split_off(start);
+        let rest = removed.split_off(end - start);
+        self.append(rest);
+    }
+
+    fn push_str(&mut self, s: &str, time: DateTime<Utc>, added: &mut String) {
+        for c in s.chars() {
+            if self.keep(c) {
+                self.push_char(c, time);
+                added.push(c);
+            }
+        }
+    }
+
+    /// Advances the escape sequence state and returns whether `c` is text.
+    fn keep(&mut self, c: char) -> bool {
+        match self.escape {
+            Escape::Start => {
+                self.escape = if c == '[' { Escape::Csi } else { Escape::None };
+                false
+            }
+            // A control character cannot be part of the sequence, which was
+            // probably garbage then
+            Escape::Csi if c.is_control() => {
+                self.escape = Escape::None;
+                self.keep(c)
+            }
+            Escape::Csi => {
+                if ('\x40'..='\x7e').contains(&c) {
+                    self.escape = Escape::None;
+                }
+                false
+            }
+            Escape::None if c == '\x1b' => {
+                self.escape = Escape::Start;
+                false
+            }
+            Escape::None => c == '\n' || c == '\t' || !c.is_control(),
+        }
+    }
+
+    fn push_char(&mut self, c: char, time: DateTime<Utc>) {
+        self.text.push(c);
+        if !self.open_line {
+            self.lines.push(Line {
+                time,
+                text: String::new(),
+            });
+        }
+        if c == '\n' {
+            self.open_line = false;
+        } else {
+            self.open_line = true;
+            if let Some(line) = self.lines.last_mut() {
+                line.text.push(c);
+            }
+        }
+    }
+}
+
+/// Line matched by a [`LineMatcher`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub line: Line,
+    /// Values of the named capture groups.
+    pub values: BTreeMap<String, String>,
+}
+
+/// Matches console output line by line against an expected or not expected
+/// pattern of the check catalog.
+#[derive(Clone, Debug)]
+pub struct LineMatcher {
+    re: Regex,
+}
+
+impl LineMatcher {
+    /// Creates a matcher for `pattern`, which is a regular expression if
+    /// `regex` is set and a plain string otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `pattern` is not a valid regular expression.
+    pub fn new(pattern: &str, regex: bool) -> Result<LineMatcher, regex::Error> {
+        let re = if regex {
+            Regex::new(pattern)?
+        } else {
+            Regex::new(&regex::escape(pattern))?
+        };
+        Ok(LineMatcher { re })
+    }
+
+    /// Returns the values of the named capture groups if `line` matches.
+    #[must_use]
+    pub fn matches(&self, line: &str) -> Option<BTreeMap<String, String>> {
+        let captures = self.re.captures(line)?;
+        Some(
+            self.re
+                .capture_names()
+                .flatten()
+                .filter_map(|name| {
+                    captures
+                        .name(name)
+                        .map(|m| (name.to_string(), m.as_str().to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the first matching line, `lines` are only consumed up to it.
+    pub fn find<'a>(&self, lines: impl IntoIterator<Item = &'a Line>) -> Option<Match> {
+        lines.into_iter().find_map(|line| {
+            self.matches(&line.text).map(|values| Match {
+                line: line.clone(),
+                values,
+            })
+        })
+    }
+}
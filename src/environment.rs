@@ -0,0 +1,185 @@
+use crate::factory_data;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Flag of the copy of the redundant environment in use.
+const ACTIVE: u8 = 1;
+/// Flag of the outdated copy, set once the other copy has been saved.
+const OBSOLETE: u8 = 0;
+/// Offset of the flag in the header of a copy, after its CRC.
+const FLAG_OFFSET: usize = 4;
+/// Bytes dumped of the header of each copy.
+const HEADER_DUMP_SIZE: usize = 0x10;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Missing(String),
+    UnexpectedValue {
+        name: String,
+        value: String,
+    },
+    /// Flags of both copies, not one active and one obsolete copy.
+    InconsistentFlags([u8; 2]),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Missing(name) => write!(f, "U-Boot variable {name} missing"),
+            Error::UnexpectedValue { name, value } => {
+                write!(f, "Unexpected U-Boot variable {name}={value}")
+            }
+            Error::InconsistentFlags([first, second]) => write!(
+                f,
+                "Inconsistent flags of the redundant U-Boot environment (0x{first:02x}, 0x{second:02x})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Regular expression the whole value of a U-Boot variable must match,
+/// compiled when the config is loaded.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ValuePattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl ValuePattern {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    #[must_use]
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+impl TryFrom<String> for ValuePattern {
+    type Error = regex::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        let regex = Regex::new(&format!("^(?:{pattern})$"))?;
+        Ok(ValuePattern { pattern, regex })
+    }
+}
+
+impl From<ValuePattern> for String {
+    fn from(pattern: ValuePattern) -> Self {
+        pattern.pattern
+    }
+}
+
+/// U-Boot environment as printed by `printenv`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Environment {
+    pub variables: BTreeMap<String, String>,
+}
+
+impl Environment {
+    /// Parses the output of `printenv`, returns `None` if it contains no
+    /// variables.
+    ///
+    /// # Panics
+    ///
+    /// Panics if something unexpected happens.
+    #[must_use]
+    pub fn parse(console_output: &str) -> Option<Environment> {
+        let variable =
+            Regex::new(r"^([\w.-]+)=(.*)$").expect("Failed to create regular expression");
+
+        let variables: BTreeMap<String, String> = console_output
+            .lines()
+            .filter_map(|line| variable.captures(line.trim_end()))
+            .map(|c| (c[1].to_string(), c[2].to_string()))
+            .collect();
+        if variables.is_empty() {
+            return None;
+        }
+        Some(Environment { variables })
+    }
+
+    /// Checks that each of the `required` variables is set and its whole
+    /// value matches the regular expression it maps to.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` for the first variable which is missing or has an
+    /// unexpected value.
+    pub fn validate(&self, required: &BTreeMap<String, ValuePattern>) -> Result<(), Error> {
+        for (name, pattern) in required {
+            let Some(value) = self.variables.get(name) else {
+                return Err(Error::Missing(name.clone()));
+            };
+            if !pattern.is_match(value) {
+                return Err(Error::UnexpectedValue {
+                    name: name.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the variables as recorded in the diagnosis, e.g. `env.bootcmd`.
+    #[must_use]
+    pub fn values(&self) -> BTreeMap<String, String> {
+        self.variables
+            .iter()
+            .map(|(name, value)| (format!("env.{name}"), value.clone()))
+            .collect()
+    }
+}
+
+/// Flags of both copies of the redundant U-Boot environment (`uboot_env0` and
+/// `uboot_env1`), they tell U-Boot which copy to use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RedundantFlags(pub [u8; 2]);
+
+impl RedundantFlags {
+    /// Parses an `md.b` dump of the headers of both copies, 16 bytes each.
+    #[must_use]
+    pub fn parse(console_output: &str) -> Option<RedundantFlags> {
+        let dump = factory_data::parse_dump(console_output);
+        Some(RedundantFlags([
+            *dump.get(FLAG_OFFSET)?,
+            *dump.get(HEADER_DUMP_SIZE + FLAG_OFFSET)?,
+        ]))
+    }
+
+    /// Checks that one copy is active and the other one obsolete, as left by
+    /// `saveenv`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if both copies have the same flag or a copy was never
+    /// saved.
+    pub fn validate(&self) -> Result<(), Error> {
+        match self.0 {
+            [ACTIVE, OBSOLETE] | [OBSOLETE, ACTIVE] => Ok(()),
+            flags => Err(Error::InconsistentFlags(flags)),
+        }
+    }
+
+    /// Returns the flags as recorded in the diagnosis.
+    #[must_use]
+    pub fn values(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (
+                String::from("env_flags.uboot_env0"),
+                format!("0x{:02x}", self.0[0]),
+            ),
+            (
+                String::from("env_flags.uboot_env1"),
+                format!("0x{:02x}", self.0[1]),
+            ),
+        ])
+    }
+}
@@ -0,0 +1,331 @@
+use crate::analyzer::{Direction, TranscriptEntry};
+use crate::console_output::ConsoleOutput;
+use crate::transport::ConsoleTransport;
+use chrono::Utc;
+use log::debug;
+use regex::Regex;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::time::{Duration, Instant};
+
+/// Printed by U-Boot when the command line is interrupted with Ctrl-C.
+static INTERRUPT: &str = "<INTERRUPT>";
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+/// Time the DUT may take to confirm an interrupt.
+const INTERRUPT_TIMEOUT: Duration = Duration::from_millis(200);
+/// Recorded in the transcript instead of a secret line.
+pub static REDACTED: &str = "***\n";
+
+#[derive(Debug)]
+pub enum Error {
+    Write(io::Error),
+    Read(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Write(e) => write!(f, "Failed to write to console: {e}"),
+            Error::Read(e) => write!(f, "Failed to read from console: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Result of [`Expect::expect`].
+#[derive(Clone, Debug)]
+pub struct MatchResult {
+    /// Index of the pattern which matched first, `None` if the deadline
+    /// passed.
+    pub matched: Option<usize>,
+    /// Output up to the end of the match, or everything received until the
+    /// deadline.
+    pub output: ConsoleOutput,
+}
+
+/// Expect-like engine talking to the console of the DUT.
+///
+/// Received output is buffered until a pattern matches and only consumed up
+/// to the end of the match, the rest is left for the next
+/// [`Expect::expect`]. The echo of a line sent with [`Expect::send_line`] is
+/// dropped, as are asynchronous messages matching [`Expect::ignore`], e.g.
+/// kernel messages printed while a Linux command runs.
+pub struct Expect<'a> {
+    console: &'a mut dyn ConsoleTransport,
+    pending: ConsoleOutput,
+    prompt: Regex,
+    /// Line sent last, which the DUT may echo.
+    echo: Option<String>,
+    ignored: Option<Regex>,
+    transcript: Vec<TranscriptEntry>,
+}
+
+impl<'a> Expect<'a> {
+    /// Creates an engine for `console` whose shell prints `prompt`.
+    pub fn new(console: &'a mut dyn ConsoleTransport, prompt: Regex) -> Expect<'a> {
+        Expect {
+            console,
+            pending: ConsoleOutput::default(),
+            prompt,
+            echo: None,
+            ignored: None,
+            transcript: Vec::new(),
+        }
+    }
+
+    /// Sets the prompt of the shell, e.g. after leaving U-Boot.
+    pub fn set_prompt(&mut self, prompt: Regex) {
+        self.prompt = prompt;
+    }
+
+    #[must_use]
+    pub fn prompt(&self) -> &Regex {
+        &self.prompt
+    }
+
+    /// Drops every complete message matching `messages` from the output,
+    /// empty matches are skipped.
+    pub fn ignore(&mut self, messages: Regex) {
+        self.ignored = Some(messages);
+    }
+
+    /// Returns everything sent to and received from the DUT.
+    #[must_use]
+    pub fn into_transcript(self) -> Vec<TranscriptEntry> {
+        self.transcript
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if writing to the console fails.
+    pub fn send(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.record(Direction::Tx, String::from_utf8_lossy(buf).into_owned());
+        self.write(buf)
+    }
+
+    /// Sends `line` as a command line.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if writing to the console fails.
+    pub fn send_line(&mut self, line: &str) -> Result<(), Error> {
+        self.send(format!("{line}\n").as_bytes())?;
+        self.echo = Some(line.trim().to_string());
+        Ok(())
+    }
+
+    /// Sends `line`, e.g. a password, which is neither echoed by the DUT nor
+    /// kept in the transcript.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if writing to the console fails.
+    pub fn send_secret_line(&mut self, line: &str) -> Result<(), Error> {
+        self.record(Direction::Tx, String::from(REDACTED));
+        self.write(format!("{line}\n").as_bytes())
+    }
+
+    /// Waits until one of `patterns` matches the output or `deadline` passes.
+    ///
+    /// The pattern matching earliest in the output wins, on equal positions
+    /// the first of `patterns`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if reading from the console fails.
+    pub fn expect(&mut self, patterns: &[&Regex], deadline: Instant) -> Result<MatchResult, Error> {
+        let (matched, end) = match self.wait(patterns, deadline)? {
+            Some((index, end)) => (Some(index), end),
+            None => (None, self.pending.text().len()),
+        };
+        Ok(MatchResult {
+            matched,
+            output: self.consume(end),
+        })
+    }
+
+    /// Waits for the prompt of the shell.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if reading from the console fails.
+    pub fn wait_prompt(&mut self, deadline: Instant) -> Result<MatchResult, Error> {
+        let prompt = self.prompt.clone();
+        self.expect(&[&prompt], deadline)
+    }
+
+    /// Sends `key` about every read until the prompt shows up, e.g. to stop
+    /// autoboot, the DUT stays silent for `idle` or `deadline` passes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the console fails.
+    pub fn wait_prompt_sending(
+        &mut self,
+        key: &[u8],
+        idle: Duration,
+        deadline: Instant,
+    ) -> Result<MatchResult, Error> {
+        let prompt = self.prompt.clone();
+        let mut received = self.pending.text().len();
+        let mut last_output = Instant::now();
+
+        let found = loop {
+            self.send(key)?;
+            let next_key = (Instant::now() + READ_TIMEOUT).min(deadline);
+            if let Some((_, end)) = self.wait(&[&prompt], next_key)? {
+                break Some(end);
+            }
+            if self.pending.text().len() > received {
+                received = self.pending.text().len();
+                last_output = Instant::now();
+            }
+            if last_output.elapsed() >= idle || Instant::now() >= deadline {
+                break None;
+            }
+        };
+
+        let end = found.unwrap_or(self.pending.text().len());
+        Ok(MatchResult {
+            matched: found.map(|_| 0),
+            output: self.consume(end),
+        })
+    }
+
+    /// Interrupts the command line with Ctrl-C, e.g. to discard keys typed to
+    /// stop autoboot, and consumes the output up to the next prompt.
+    ///
+    /// An interrupt which is not confirmed before the end of the current line,
+    /// e.g. in a scripted session, leaves the output untouched.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the console fails.
+    pub fn interrupt(&mut self) -> Result<(), Error> {
+        self.send(b"\x03")?;
+
+        let interrupt = literal(INTERRUPT);
+        let line_end = literal("\n");
+        let deadline = Instant::now() + INTERRUPT_TIMEOUT;
+        if let Some((0, end)) = self.wait(&[&interrupt, &line_end], deadline)? {
+            self.consume(end);
+            self.wait_prompt(Instant::now() + INTERRUPT_TIMEOUT)?;
+        }
+        Ok(())
+    }
+
+    /// Receives until one of `patterns` matches, returns the index of the
+    /// pattern and the end of the match in the pending output.
+    fn wait(
+        &mut self,
+        patterns: &[&Regex],
+        deadline: Instant,
+    ) -> Result<Option<(usize, usize)>, Error> {
+        loop {
+            self.drop_echo();
+            self.drop_ignored();
+            if let Some(found) = find_first(patterns, self.pending.text()) {
+                return Ok(Some(found));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            self.receive()?;
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.console.write_all(buf).map_err(Error::Write)?;
+        self.console.flush().map_err(Error::Write)
+    }
+
+    fn receive(&mut self) -> Result<(), Error> {
+        let mut buf = [0; 1000];
+        let bytes_read = match self.console.read(&mut buf, READ_TIMEOUT) {
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => 0,
+            Err(e) => return Err(Error::Read(e)),
+        };
+        let s = self.pending.push(&buf[..bytes_read], Utc::now());
+        if !s.is_empty() {
+            debug!("{s}");
+            self.record(Direction::Rx, s);
+        }
+        Ok(())
+    }
+
+    /// Removes the pending output up to `end` and returns it.
+    fn consume(&mut self, end: usize) -> ConsoleOutput {
+        let rest = self.pending.split_off(end);
+        std::mem::replace(&mut self.pending, rest)
+    }
+
+    /// Drops the echo of the line sent last once the first line of the
+    /// output is complete, the DUT does not echo if it differs.
+    fn drop_echo(&mut self) {
+        let Some(echo) = &self.echo else {
+            return;
+        };
+        let text = self.pending.text();
+        let Some(end) = text.find('\n') else {
+            return;
+        };
+        if text[..end].trim() == echo {
+            self.consume(end + 1);
+        }
+        self.echo = None;
+    }
+
+    fn drop_ignored(&mut self) {
+        let Some(ignored) = &self.ignored else {
+            return;
+        };
+        while let Some(m) = ignored
+            .find_iter(self.pending.text())
+            .find(|m| !m.is_empty())
+        {
+            let (start, end) = (m.start(), m.end());
+            self.pending.remove(start, end);
+        }
+    }
+
+    fn record(&mut self, direction: Direction, text: String) {
+        self.transcript.push(TranscriptEntry {
+            time: Utc::now(),
+            direction,
+            text,
+        });
+    }
+}
+
+/// Returns a pattern matching `text` literally.
+///
+/// # Panics
+///
+/// Panics if something unexpected happens.
+#[must_use]
+pub fn literal(text: &str) -> Regex {
+    regex(&regex::escape(text))
+}
+
+/// Returns the regular expression `pattern`.
+///
+/// # Panics
+///
+/// Panics if `pattern` is invalid, patterns are part of the code.
+#[must_use]
+pub fn regex(pattern: &str) -> Regex {
+    Regex::new(pattern).expect("Invalid pattern")
+}
+
+/// Returns the index of the pattern matching earliest in `text` and the end
+/// of its match.
+fn find_first(patterns: &[&Regex], text: &str) -> Option<(usize, usize)> {
+    patterns
+        .iter()
+        .enumerate()
+        .filter_map(|(i, re)| re.find(text).map(|m| (m.start(), i, m.end())))
+        .min()
+        .map(|(_, i, end)| (i, end))
+}
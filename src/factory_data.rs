@@ -0,0 +1,217 @@
+use crate::iprid;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Size of the factory data at the start of the `factory` partition.
+pub const SIZE: usize = 0x1f0;
+
+/// Start of the values written in production, the bytes before are the
+/// calibration data of the MT7688 including its MAC addresses.
+const USER_OFFSET: usize = 0x140;
+const WIFI_MAC_OFFSET: usize = 0x04;
+const ETH_MAC_OFFSET: usize = 0x28;
+const MAGIC: u32 = 0xcafe_babe;
+const UUID_LEN: usize = 36;
+
+/// Factory data versions understood by this release.
+pub const SUPPORTED_VERSIONS: &[u32] = &[1];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Incomplete(usize),
+    InvalidMagic(u32),
+    InvalidCrc { stored: u32, computed: u32 },
+    UnsupportedVersion(u32),
+    MissingMac(&'static str),
+    DuplicateMac(MacAddress),
+    InvalidIprid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Incomplete(len) => {
+                write!(f, "Factory data incomplete ({len} of {SIZE} bytes)")
+            }
+            Error::InvalidMagic(magic) => {
+                write!(f, "Factory data missing (magic 0x{magic:08x})")
+            }
+            Error::InvalidCrc { stored, computed } => write!(
+                f,
+                "Factory data corrupt (CRC 0x{stored:08x}, expected 0x{computed:08x})"
+            ),
+            Error::UnsupportedVersion(v) => write!(f, "Unknown factory data version {v}"),
+            Error::MissingMac(name) => write!(f, "{name} MAC address missing in factory data"),
+            Error::DuplicateMac(mac) => {
+                write!(
+                    f,
+                    "Same MAC address {mac} for WiFi and Ethernet in factory data"
+                )
+            }
+            Error::InvalidIprid(iprid) => write!(f, "Invalid IPRID \"{iprid}\" in factory data"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl MacAddress {
+    /// Returns whether the address was never written, i.e. is all zeros or
+    /// erased flash.
+    #[must_use]
+    pub fn is_unset(&self) -> bool {
+        self.0.iter().all(|&b| b == 0x00) || self.0.iter().all(|&b| b == 0xff)
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let octets: Vec<String> = self.0.iter().map(|b| format!("{b:02x}")).collect();
+        write!(f, "{}", octets.join(":"))
+    }
+}
+
+/// Factory data as written in production to the `factory` partition of the
+/// SPI-NOR flash and read by U-Boot (`F-Data:` in the boot banner).
+///
+/// Version 1 stores no hardware revision, the HQV ID identifies the hardware
+/// variant instead.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FactoryData {
+    pub version: u32,
+    pub crc: u32,
+    pub wifi_mac: MacAddress,
+    pub eth_mac: MacAddress,
+    pub iprid: String,
+    pub hqv_id: String,
+    pub unielec_id: String,
+}
+
+impl FactoryData {
+    /// Decodes the first [`SIZE`] bytes of the `factory` partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `bytes` is too short, the magic value or the CRC
+    /// is wrong or the version is unknown.
+    pub fn decode(bytes: &[u8]) -> Result<FactoryData, Error> {
+        if bytes.len() < SIZE {
+            return Err(Error::Incomplete(bytes.len()));
+        }
+        let bytes = &bytes[..SIZE];
+        let user = &bytes[USER_OFFSET..];
+
+        let magic = u32_at(user, 4);
+        if magic != MAGIC {
+            return Err(Error::InvalidMagic(magic));
+        }
+
+        let stored = u32_at(user, 0);
+        let computed = crc32fast::hash(&user[4..]);
+        if stored != computed {
+            return Err(Error::InvalidCrc { stored, computed });
+        }
+
+        let version = u32_at(user, 8);
+        if !SUPPORTED_VERSIONS.contains(&version) {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        Ok(FactoryData {
+            version,
+            crc: stored,
+            wifi_mac: mac_at(bytes, WIFI_MAC_OFFSET),
+            eth_mac: mac_at(bytes, ETH_MAC_OFFSET),
+            iprid: uuid_at(user, 12),
+            hqv_id: uuid_at(user, 12 + UUID_LEN),
+            unielec_id: uuid_at(user, 12 + 2 * UUID_LEN),
+        })
+    }
+
+    /// Checks the decoded values for obvious production errors.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` describing the first invalid value.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.wifi_mac.is_unset() {
+            return Err(Error::MissingMac("WiFi"));
+        }
+        if self.eth_mac.is_unset() {
+            return Err(Error::MissingMac("Ethernet"));
+        }
+        if self.wifi_mac == self.eth_mac {
+            return Err(Error::DuplicateMac(self.eth_mac));
+        }
+        if !iprid::is_valid(&self.iprid) {
+            return Err(Error::InvalidIprid(self.iprid.clone()));
+        }
+        Ok(())
+    }
+
+    /// Returns the decoded values as recorded in the diagnosis.
+    #[must_use]
+    pub fn values(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (
+                String::from("factory_data_version"),
+                self.version.to_string(),
+            ),
+            (
+                String::from("factory_data_crc"),
+                format!("0x{:08x}", self.crc),
+            ),
+            (String::from("wifi_mac"), self.wifi_mac.to_string()),
+            (String::from("eth_mac"), self.eth_mac.to_string()),
+            (String::from("iprid"), self.iprid.clone()),
+            (String::from("hqv_id"), self.hqv_id.clone()),
+            (String::from("unielec_id"), self.unielec_id.clone()),
+        ])
+    }
+}
+
+/// Extracts the bytes of a U-Boot `md.b` dump from `console_output`.
+///
+/// # Panics
+///
+/// Panics if something unexpected happens.
+#[must_use]
+pub fn parse_dump(console_output: &str) -> Vec<u8> {
+    let line = Regex::new(r"(?m)^[0-9a-f]{8}:((?: [0-9a-f]{2}){1,16})")
+        .expect("Failed to create regular expression");
+
+    line.captures_iter(console_output)
+        .flat_map(|c| {
+            c[1].split_whitespace()
+                .filter_map(|b| u8::from_str_radix(b, 16).ok())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    let mut word = [0; 4];
+    word.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(word)
+}
+
+fn mac_at(bytes: &[u8], offset: usize) -> MacAddress {
+    let mut mac = [0; 6];
+    mac.copy_from_slice(&bytes[offset..offset + 6]);
+    MacAddress(mac)
+}
+
+/// Returns the UUID string at `offset`, which is not terminated if it uses
+/// the whole field.
+fn uuid_at(bytes: &[u8], offset: usize) -> String {
+    bytes[offset..offset + UUID_LEN]
+        .iter()
+        .take_while(|&&b| b != 0x00 && b != 0xff)
+        .map(|&b| char::from(b))
+        .collect()
+}
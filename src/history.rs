@@ -0,0 +1,96 @@
+use crate::iprid;
+use crate::report::Report;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+/// Earlier diagnosis of a unit.
+#[derive(Debug)]
+pub struct Entry {
+    pub iprid: String,
+    pub timestamp: DateTime<Utc>,
+    pub station: String,
+    pub healthy: bool,
+    pub message: String,
+}
+
+/// Local database of all diagnosed units.
+pub struct History {
+    connection: Connection,
+}
+
+impl History {
+    /// Opens the database at `path`, creating it if necessary.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the database cannot be opened or initialized.
+    pub fn open(path: &Path) -> rusqlite::Result<History> {
+        History::init(Connection::open(path)?)
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the database cannot be initialized.
+    pub fn open_in_memory() -> rusqlite::Result<History> {
+        History::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> rusqlite::Result<History> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS diagnosis (
+                id INTEGER PRIMARY KEY,
+                iprid TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                station TEXT NOT NULL,
+                healthy INTEGER NOT NULL,
+                message TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS diagnosis_iprid ON diagnosis (iprid);",
+        )?;
+        Ok(History { connection })
+    }
+
+    /// Stores the diagnosis under the normalized IPRID of the unit.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the diagnosis cannot be stored.
+    pub fn record(&self, report: &Report) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT INTO diagnosis (iprid, timestamp, station, healthy, message)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                iprid::normalize(report.iprid),
+                report.timestamp,
+                report.station.name,
+                report.diagnosis.healthy,
+                report.diagnosis.message,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns all diagnoses of `iprid`, most recent first, regardless of the
+    /// separators it was scanned with.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the database cannot be queried.
+    pub fn entries(&self, iprid: &str) -> rusqlite::Result<Vec<Entry>> {
+        let mut statement = self.connection.prepare(
+            "SELECT iprid, timestamp, station, healthy, message FROM diagnosis
+             WHERE iprid = ?1 ORDER BY timestamp DESC, id DESC",
+        )?;
+        let entries = statement.query_map([iprid::normalize(iprid)], |row| {
+            Ok(Entry {
+                iprid: row.get(0)?,
+                timestamp: row.get(1)?,
+                station: row.get(2)?,
+                healthy: row.get(3)?,
+                message: row.get(4)?,
+            })
+        })?;
+        entries.collect()
+    }
+}
@@ -0,0 +1,21 @@
+use regex::Regex;
+
+/// Checks whether `iprid` has the format of an IPRID as printed in the QR code.
+///
+/// # Panics
+///
+/// Panics if something unexpected happens.
+#[must_use]
+pub fn is_valid(iprid: &str) -> bool {
+    Regex::new(r"^[0-9a-f]{8}[-']([0-9a-f]{4}[-']){3}[0-9a-f]{12}$")
+        .expect("Failed to create regular expression")
+        .is_match(iprid)
+}
+
+/// Returns `iprid` in the format stored in the factory data.
+///
+/// Scanned IPRIDs may use `'` instead of `-` as separator.
+#[must_use]
+pub fn normalize(iprid: &str) -> String {
+    iprid.trim().replace('\'', "-").to_lowercase()
+}
@@ -1,29 +1,62 @@
-use core::time::Duration;
-use serialport::SerialPort;
+use crate::transport::{ConsoleTransport, SerialTransport, TcpTransport};
+use chrono::{DateTime, Utc};
+use std::fmt;
 
+#[derive(Debug)]
+pub enum Error {
+    Open(serialport::Error),
+    Connect(std::io::Error),
+    PowerOn(std::io::Error),
+    PowerOff(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Open(e) => write!(f, "Failed to open serial port: {e}"),
+            Error::Connect(e) => write!(f, "Failed to connect to console server: {e}"),
+            Error::PowerOn(e) => write!(f, "Failed to power on the DUT: {e}"),
+            Error::PowerOff(e) => write!(f, "Failed to power off the DUT: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Opens the DUT console at `port`, which is either the path of a serial
+/// port or `tcp://<host>:<port>` of a console server.
+///
 /// # Errors
 ///
-/// Will return `Err` if serial port cannot be opened.
-pub fn open_serial_port(path: &str) -> Result<Box<dyn SerialPort>, serialport::Error> {
-    serialport::new(path, 115_200)
-        .timeout(Duration::from_millis(100))
-        .open()
+/// Will return `Err` if the console cannot be opened.
+pub fn open_console(port: &str) -> Result<Box<dyn ConsoleTransport>, Error> {
+    if let Some(addr) = port.strip_prefix("tcp://") {
+        let transport = TcpTransport::connect(addr).map_err(Error::Connect)?;
+        return Ok(Box::new(transport));
+    }
+
+    let transport = SerialTransport::open(port).map_err(Error::Open)?;
+    Ok(Box::new(transport))
 }
 
-/// # Panics
+/// Returns when the DUT was powered on, its boot is timed from then.
 ///
-/// Panics if something unexpected happens.
-pub fn power_on_dut(serial_port: &mut Box<dyn SerialPort>, invert_rts: bool) {
-    serial_port
-        .write_request_to_send(!invert_rts)
-        .expect("Failed power on the DUT");
+/// # Errors
+///
+/// Will return `Err` if the power control line cannot be set.
+pub fn power_on_dut(
+    console: &mut dyn ConsoleTransport,
+    invert_rts: bool,
+) -> Result<DateTime<Utc>, Error> {
+    console
+        .set_power_line(!invert_rts)
+        .map_err(Error::PowerOn)?;
+    Ok(Utc::now())
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Panics if something unexpected happens.
-pub fn power_off_dut(serial_port: &mut Box<dyn SerialPort>, invert_rts: bool) {
-    serial_port
-        .write_request_to_send(invert_rts)
-        .expect("Failed power off the DUT");
+/// Will return `Err` if the power control line cannot be set.
+pub fn power_off_dut(console: &mut dyn ConsoleTransport, invert_rts: bool) -> Result<(), Error> {
+    console.set_power_line(invert_rts).map_err(Error::PowerOff)
 }
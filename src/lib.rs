@@ -1,3 +1,20 @@
 pub mod analyzer;
+pub mod boot_profile;
+pub mod boot_timing;
+pub mod capture;
+pub mod catalog;
 pub mod config;
+pub mod console_output;
+pub mod environment;
+pub mod expect;
+pub mod factory_data;
+pub mod history;
+pub mod iprid;
 pub mod jig;
+pub mod mtd;
+pub mod nand;
+pub mod nor;
+pub mod report;
+pub mod reset;
+pub mod simulator;
+pub mod transport;
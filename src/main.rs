@@ -1,28 +1,46 @@
+mod cli;
+
+use clap::Parser;
 use log::{error, info};
-use serialport::SerialPort;
-use smart_garden_gateway_doctor::analyzer::{analyze, Diagnosis};
+use smart_garden_gateway_doctor::analyzer::{diagnose, Diagnosis};
+use smart_garden_gateway_doctor::capture::RecordingTransport;
 use smart_garden_gateway_doctor::config::Config;
-use smart_garden_gateway_doctor::jig::{open_serial_port, power_off_dut, power_on_dut};
-use std::fs::File;
-use std::io::prelude::*;
+use smart_garden_gateway_doctor::history::{self, History};
+use smart_garden_gateway_doctor::iprid;
+use smart_garden_gateway_doctor::jig::{open_console, power_off_dut};
+use smart_garden_gateway_doctor::report::Report;
+use smart_garden_gateway_doctor::transport::ConsoleTransport;
+use std::process::ExitCode;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 static TITLE: &str = "GARDENA smart Gateway Doctor";
+
 static SPACING: f32 = 20.0;
+static INSTRUCTIONS_STATION_FAULT: &str = "Check jig and serial adapter, then repeat the test";
+
+#[derive(Parser)]
+#[command(version, about = TITLE)]
+struct Args {
+    /// Run headless instead of starting the GUI
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+}
 
 struct App {
     lm_id: String,
     serial_port_list: Vec<String>,
     serial_port_index: usize,
-    serial_port: Option<Arc<Mutex<Box<dyn SerialPort>>>>,
+    serial_port: Option<Arc<Mutex<Box<dyn ConsoleTransport>>>>,
     message: String,
     message_color: egui::Color32,
     instructions: String,
+    other_issues: Vec<String>,
+    history: Vec<history::Entry>,
     busy: bool,
-    tx: Sender<Diagnosis>,
-    rx: Receiver<Diagnosis>,
+    tx: Sender<Result<Diagnosis, String>>,
+    rx: Receiver<Result<Diagnosis, String>>,
 }
 
 impl Default for App {
@@ -38,6 +56,8 @@ impl Default for App {
             message: String::new(),
             message_color: egui::Color32::default(),
             instructions: String::new(),
+            other_issues: Vec::new(),
+            history: Vec::new(),
             busy: false,
             tx,
             rx,
@@ -50,12 +70,19 @@ impl eframe::App for App {
         self.update_serial_port_info();
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.label(egui::RichText::new(TITLE).color(egui::Color32::WHITE).size(20.0));
-            
+            ui.label(
+                egui::RichText::new(TITLE)
+                    .color(egui::Color32::WHITE)
+                    .size(20.0),
+            );
 
             ui.add(egui::Separator::default().spacing(SPACING));
             ui.horizontal(|ui| {
-                ui.label(egui::RichText::new("Scan IPRID QR code: ").color(egui::Color32::WHITE).size(14.0));
+                ui.label(
+                    egui::RichText::new("Scan IPRID QR code: ")
+                        .color(egui::Color32::WHITE)
+                        .size(14.0),
+                );
 
                 let field_resp = ui.add_sized(
                     ui.available_size(),
@@ -73,15 +100,41 @@ impl eframe::App for App {
             ui.add(egui::Separator::default().spacing(SPACING));
 
             ui.horizontal(|ui| {
-                ui.label(egui::RichText::new("Issue:").color(egui::Color32::WHITE).size(13.0));
+                ui.label(
+                    egui::RichText::new("Issue:")
+                        .color(egui::Color32::WHITE)
+                        .size(13.0),
+                );
 
                 ui.colored_label(self.message_color, &self.message);
             });
             ui.horizontal(|ui| {
-                ui.label(egui::RichText::new("Instructions:").color(egui::Color32::WHITE).size(13.0));
+                ui.label(
+                    egui::RichText::new("Instructions:")
+                        .color(egui::Color32::WHITE)
+                        .size(13.0),
+                );
 
                 ui.colored_label(self.message_color, &self.instructions);
             });
+            if !self.other_issues.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        egui::RichText::new("Other issues:")
+                            .color(egui::Color32::WHITE)
+                            .size(13.0),
+                    );
+
+                    ui.vertical(|ui| {
+                        for issue in &self.other_issues {
+                            ui.colored_label(self.message_color, issue);
+                        }
+                    });
+                });
+            }
+            if !self.history.is_empty() {
+                self.show_history(ui);
+            }
 
             ui.add(egui::Separator::default().spacing(SPACING));
 
@@ -108,18 +161,7 @@ impl eframe::App for App {
             self.open_serial_port();
         }
 
-        if let Ok(diagnosis) = self.rx.try_recv() {
-            self.message = String::from(diagnosis.message);
-            if let Some(instructions) = diagnosis.instructions {
-                self.instructions = String::from(instructions);
-            }
-            self.message_color = if diagnosis.healthy {
-                egui::Color32::GREEN
-            } else {
-                egui::Color32::RED
-            };
-            self.busy = false;
-        }
+        self.receive_diagnosis();
 
         std::thread::sleep(Duration::from_millis(100));
         ctx.request_repaint();
@@ -127,15 +169,87 @@ impl eframe::App for App {
 }
 
 impl App {
+    fn show_history(&self, ui: &mut egui::Ui) {
+        ui.add(egui::Separator::default().spacing(SPACING));
+
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("History:")
+                    .color(egui::Color32::WHITE)
+                    .size(13.0),
+            );
+
+            ui.vertical(|ui| {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    format!(
+                        "Repeat return, diagnosed {} times before",
+                        self.history.len()
+                    ),
+                );
+                for entry in &self.history {
+                    let color = if entry.healthy {
+                        egui::Color32::GREEN
+                    } else {
+                        egui::Color32::RED
+                    };
+                    ui.colored_label(
+                        color,
+                        format!(
+                            "{} {}: {}",
+                            entry.timestamp.format("%Y-%m-%d %H:%M"),
+                            entry.station,
+                            entry.message
+                        ),
+                    );
+                }
+            });
+        });
+    }
+
+    fn receive_diagnosis(&mut self) {
+        match self.rx.try_recv() {
+            Ok(Ok(diagnosis)) => {
+                self.other_issues = diagnosis
+                    .failures()
+                    .skip(1)
+                    .map(|r| format!("{} ({})", r.message, r.instructions))
+                    .collect();
+                self.message = diagnosis.message;
+                if let Some(instructions) = diagnosis.instructions {
+                    self.instructions = instructions;
+                }
+                self.message_color = if diagnosis.healthy {
+                    egui::Color32::GREEN
+                } else {
+                    egui::Color32::RED
+                };
+                self.busy = false;
+            }
+            Ok(Err(station_fault)) => {
+                error!("{station_fault}");
+                self.message = format!("Station fault: {station_fault}");
+                self.instructions = String::from(INSTRUCTIONS_STATION_FAULT);
+                self.message_color = egui::Color32::YELLOW;
+                self.busy = false;
+            }
+            Err(_) => {}
+        }
+    }
+
     fn update_serial_port_info(&mut self) {
         if let Ok(ports) = serialport::available_ports() {
             let mut port_name = &self.serial_port_list[self.serial_port_index];
-            let config = Config::new();
+            let config = Config::new().unwrap_or_default();
             let configured_port = &config.serial_port;
             if !configured_port.is_empty() {
                 port_name = configured_port;
             }
             let mut port_names: Vec<String> = ports.into_iter().map(|p| p.port_name).collect();
+            // Console servers and pseudo-terminals are not enumerated
+            if !configured_port.is_empty() && !port_names.contains(configured_port) {
+                port_names.push(configured_port.clone());
+            }
             port_names.sort();
             let mut port_index = 0;
             if let Ok(i) = port_names.binary_search_by(|s| s.cmp(port_name)) {
@@ -158,22 +272,34 @@ impl App {
 
             let serial_port_name = self.serial_port_list[self.serial_port_index].clone();
 
-            if let Ok(serial_port) = open_serial_port(&serial_port_name) {
-                info!("Successfully opened serial port {serial_port_name}");
-                self.serial_port = Some(Arc::new(Mutex::new(serial_port)));
+            match open_console(&serial_port_name) {
+                Ok(serial_port) => {
+                    info!("Successfully opened serial port {serial_port_name}");
+                    self.serial_port = Some(Arc::new(Mutex::new(serial_port)));
+
+                    // A malformed config file is not overwritten
+                    let mut config = match Config::new() {
+                        Ok(config) => config,
+                        Err(e) => {
+                            error!("{e}");
+                            return;
+                        }
+                    };
 
-                let mut config = Config::new();
+                    if let Some(s) = self.serial_port.clone().filter(|_| config.power_control) {
+                        if let Ok(mut serial_port) = s.lock() {
+                            if let Err(e) = power_off_dut(serial_port.as_mut(), config.invert_rts) {
+                                error!("{e}");
+                            }
+                        }
+                    }
 
-                if let Some(s) = self.serial_port.clone() {
-                    if let Ok(mut serial_port) = s.lock() {
-                        power_off_dut(&mut serial_port, config.invert_rts);
+                    config.serial_port = serial_port_name;
+                    if let Err(e) = config.save() {
+                        error!("{e}");
                     }
                 }
-
-                config.serial_port = serial_port_name;
-                config.save();
-            } else {
-                error!("Failed to open serial port {serial_port_name}");
+                Err(e) => error!("{e} ({serial_port_name})"),
             }
         }
     }
@@ -190,15 +316,16 @@ impl App {
 
         self.message.clear();
         self.instructions.clear();
-
-        let re = regex::Regex::new(r"^[0-9a-f]{8}[-']([0-9a-f]{4}[-']){3}[0-9a-f]{12}$")
-            .expect("Failed to create regular expression");
-        if re.is_match(self.lm_id.as_str()) {
-            self.run();
-
-            let file_name = format!("{}.txt", self.lm_id);
-            if let Err(e) = write_to_file(&file_name, &self.lm_id) {
-                error!("Failed to write to file: {}", e);
+        self.other_issues.clear();
+        self.history.clear();
+
+        if iprid::is_valid(&self.lm_id) {
+            match Config::new() {
+                Ok(config) => {
+                    self.load_history(&config);
+                    self.run(config);
+                }
+                Err(e) => self.abort(&e.to_string()),
             }
         } else {
             self.abort("Invalid IPRID entered");
@@ -207,33 +334,74 @@ impl App {
         self.lm_id.clear();
     }
 
-    fn run(&mut self) {
+    fn load_history(&mut self, config: &Config) {
+        match History::open(&config.history_db).and_then(|h| h.entries(&self.lm_id)) {
+            Ok(entries) => {
+                if !entries.is_empty() {
+                    info!("Unit was diagnosed {} times before", entries.len());
+                }
+                self.history = entries;
+            }
+            Err(e) => error!("Failed to read history: {e}"),
+        }
+    }
+
+    fn run(&mut self, config: Config) {
+        let catalog = match config.load_catalog() {
+            Ok(catalog) => catalog,
+            Err(e) => {
+                self.abort(&e.to_string());
+                return;
+            }
+        };
+
         if let Some(s) = &self.serial_port {
             let s = s.clone();
             let tx = self.tx.clone();
             let lm_id = self.lm_id.clone();
             std::thread::spawn(move || {
-                if let Ok(mut serial_port) = s.try_lock() {
+                let result = if let Ok(mut serial_port) = s.try_lock() {
                     info!("Starting diagnosis...");
 
-                    let config = Config::new();
-                    power_on_dut(&mut serial_port, config.invert_rts);
-                    let diagnosis = analyze(&mut serial_port, &lm_id); 
-                    power_off_dut(&mut serial_port, config.invert_rts);
-
-                    if tx.send(diagnosis).is_err() {
-                        error!("Failed to send diagnosis to main thread");
+                    let mut recorder = RecordingTransport::new(serial_port.as_mut());
+                    let mut result =
+                        diagnose(&mut recorder, &catalog, &config).map_err(|e| e.to_string());
+                    if let Ok(diagnosis) = &mut result {
+                        diagnosis.verify_iprid(&lm_id);
+                    }
+                    if let Ok(diagnosis) = &result {
+                        let report = Report::new(&lm_id, &config, &catalog, diagnosis);
+                        match report.save(&config.report_dir) {
+                            Ok(path) => {
+                                info!("Report written to {}", path.display());
+                                if config.record {
+                                    let mut capture = recorder.into_capture();
+                                    capture.message.clone_from(&diagnosis.message);
+                                    capture.set_setup(&catalog, &config);
+                                    let path = path.with_extension("capture.toml");
+                                    match capture.save(&path) {
+                                        Ok(()) => info!("Capture written to {}", path.display()),
+                                        Err(e) => error!("{e}"),
+                                    }
+                                }
+                            }
+                            Err(e) => error!("Failed to write report: {e}"),
+                        }
+                        if let Err(e) =
+                            History::open(&config.history_db).and_then(|h| h.record(&report))
+                        {
+                            error!("Failed to record diagnosis in history: {e}");
+                        }
                     }
+
                     info!("Done");
+                    result
                 } else {
-                    let diagnosis = Diagnosis {
-                        message: "Failed to access serial port",
-                        healthy: false,
-                        ..Default::default()
-                    };
-                    if tx.send(diagnosis).is_err() {
-                        error!("Failed to send diagnosis to main thread");
-                    }
+                    Err(String::from("Failed to access serial port"))
+                };
+
+                if tx.send(result).is_err() {
+                    error!("Failed to send diagnosis to main thread");
                 }
             });
         } else {
@@ -242,17 +410,17 @@ impl App {
     }
 }
 
-fn write_to_file(file_name: &str, content: &str) -> std::io::Result<()> {
-    let mut file = File::create(file_name)?;
-    file.write_all(content.as_bytes())?;
-    Ok(())
-}
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if let Some(command) = args.command {
+        return cli::run(command);
+    }
 
-fn main() {
     egui_logger::init_with_max_level(log::LevelFilter::Debug).unwrap();
     let _ = eframe::run_native(
         TITLE,
         eframe::NativeOptions::default(),
         Box::new(|_cc| Box::<App>::default()),
     );
+    ExitCode::SUCCESS
 }
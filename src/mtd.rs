@@ -0,0 +1,193 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Expected partitions by device and partition name, e.g. `nor0` and `uboot`.
+pub type Layout = BTreeMap<String, BTreeMap<String, Range>>;
+
+/// Part of an MTD device in bytes.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Range {
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:08x}-0x{:08x}", self.offset, self.offset + self.size)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Partition {
+    pub name: String,
+    pub range: Range,
+}
+
+/// MTD device as listed by U-Boot's `mtd list`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Device {
+    pub name: String,
+    /// Type, e.g. "NOR flash".
+    pub kind: String,
+    pub block_size: Option<u64>,
+    pub partitions: Vec<Partition>,
+}
+
+/// Difference between the partitions of the DUT and the expected layout,
+/// e.g. due to a wrong U-Boot build or a corrupt device tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Deviation {
+    MissingDevice(String),
+    MissingPartition {
+        device: String,
+        name: String,
+    },
+    /// Partition with a different offset or size.
+    ResizedPartition {
+        device: String,
+        name: String,
+        expected: Range,
+        actual: Range,
+    },
+    ExtraPartition {
+        device: String,
+        name: String,
+    },
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Deviation::MissingDevice(device) => write!(f, "{device} missing"),
+            Deviation::MissingPartition { device, name } => {
+                write!(f, "partition \"{name}\" missing on {device}")
+            }
+            Deviation::ResizedPartition {
+                device,
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "partition \"{name}\" on {device} at {actual} instead of {expected}"
+            ),
+            Deviation::ExtraPartition { device, name } => {
+                write!(f, "unexpected partition \"{name}\" on {device}")
+            }
+        }
+    }
+}
+
+/// Parses the output of `mtd list` into its devices and their partitions.
+///
+/// # Panics
+///
+/// Panics if something unexpected happens.
+#[must_use]
+pub fn parse_list(console_output: &str) -> Vec<Device> {
+    let range = Regex::new(r#"^\s*- 0x([0-9a-f]+)-0x([0-9a-f]+) : "([^"]*)""#)
+        .expect("Failed to create regular expression");
+    let property =
+        Regex::new(r"^\s*- ([^:]+): (.*)$").expect("Failed to create regular expression");
+    let mut devices: Vec<Device> = Vec::new();
+
+    for line in console_output.lines() {
+        if let Some(name) = line.strip_prefix("* ") {
+            devices.push(Device {
+                name: name.trim().to_string(),
+                ..Device::default()
+            });
+            continue;
+        }
+        let Some(device) = devices.last_mut() else {
+            continue;
+        };
+
+        if let Some(c) = range.captures(line) {
+            let start = u64::from_str_radix(&c[1], 16).unwrap_or_default();
+            let end = u64::from_str_radix(&c[2], 16).unwrap_or_default();
+            // The first range is the whole device
+            if c[3] != device.name {
+                device.partitions.push(Partition {
+                    name: c[3].to_string(),
+                    range: Range {
+                        offset: start,
+                        size: end.saturating_sub(start),
+                    },
+                });
+            }
+        } else if let Some(c) = property.captures(line) {
+            match &c[1] {
+                "type" => device.kind = c[2].trim().to_string(),
+                "block size" => {
+                    device.block_size = c[2]
+                        .trim()
+                        .strip_prefix("0x")
+                        .and_then(|s| s.split_whitespace().next())
+                        .and_then(|s| u64::from_str_radix(s, 16).ok());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    devices
+}
+
+/// Compares `devices` with `expected`, devices not in `expected` are not
+/// compared.
+#[must_use]
+pub fn compare(devices: &[Device], expected: &Layout) -> Vec<Deviation> {
+    let mut deviations = Vec::new();
+
+    for (device_name, partitions) in expected {
+        let Some(device) = devices.iter().find(|d| &d.name == device_name) else {
+            deviations.push(Deviation::MissingDevice(device_name.clone()));
+            continue;
+        };
+
+        for (name, &range) in partitions {
+            match device.partitions.iter().find(|p| &p.name == name) {
+                None => deviations.push(Deviation::MissingPartition {
+                    device: device_name.clone(),
+                    name: name.clone(),
+                }),
+                Some(partition) if partition.range != range => {
+                    deviations.push(Deviation::ResizedPartition {
+                        device: device_name.clone(),
+                        name: name.clone(),
+                        expected: range,
+                        actual: partition.range,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        for partition in &device.partitions {
+            if !partitions.contains_key(&partition.name) {
+                deviations.push(Deviation::ExtraPartition {
+                    device: device_name.clone(),
+                    name: partition.name.clone(),
+                });
+            }
+        }
+    }
+
+    deviations
+}
+
+/// Returns the partitions as recorded in the diagnosis, e.g.
+/// `mtd.nor0.uboot`.
+#[must_use]
+pub fn values(devices: &[Device]) -> BTreeMap<String, String> {
+    devices
+        .iter()
+        .flat_map(|d| {
+            d.partitions
+                .iter()
+                .map(|p| (format!("mtd.{}.{}", d.name, p.name), p.range.to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,79 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Printed by U-Boot's `mtd bad` before the offsets of the bad blocks.
+static BAD_BLOCKS_HEADER: &str = "bad blocks list:";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    TooManyBadBlocks { count: usize, max: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TooManyBadBlocks { count, max } => {
+                write!(f, "NAND worn, too many bad blocks ({count}, at most {max})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Bad blocks of the NAND flash as listed by U-Boot's `mtd bad spi-nand0`.
+///
+/// Some bad blocks are normal, they are marked by the manufacturer or wear
+/// out over the life of the flash and are skipped by UBI.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BadBlocks {
+    /// Offsets of the bad blocks in bytes.
+    pub offsets: Vec<u64>,
+}
+
+impl BadBlocks {
+    /// Parses the output of `mtd bad`, returns `None` if it contains no bad
+    /// block list, e.g. if the NAND flash was not found.
+    ///
+    /// # Panics
+    ///
+    /// Panics if something unexpected happens.
+    #[must_use]
+    pub fn parse(console_output: &str) -> Option<BadBlocks> {
+        let (_, list) = console_output.split_once(BAD_BLOCKS_HEADER)?;
+        let offset =
+            Regex::new(r"^\s*0x([0-9a-f]+)\s*$").expect("Failed to create regular expression");
+
+        let offsets = list
+            .lines()
+            .filter_map(|line| offset.captures(line))
+            .filter_map(|c| u64::from_str_radix(&c[1], 16).ok())
+            .collect();
+        Some(BadBlocks { offsets })
+    }
+
+    /// Checks that the flash has at most `max` bad blocks.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there are more bad blocks.
+    pub fn validate(&self, max: usize) -> Result<(), Error> {
+        let count = self.offsets.len();
+        if count > max {
+            return Err(Error::TooManyBadBlocks { count, max });
+        }
+        Ok(())
+    }
+
+    /// Returns the bad blocks as recorded in the diagnosis.
+    #[must_use]
+    pub fn values(&self) -> BTreeMap<String, String> {
+        let offsets: Vec<String> = self.offsets.iter().map(|o| format!("0x{o:08x}")).collect();
+        BTreeMap::from([
+            (String::from("bad_blocks"), self.offsets.len().to_string()),
+            (String::from("bad_block_offsets"), offsets.join(" ")),
+        ])
+    }
+}
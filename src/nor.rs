@@ -0,0 +1,155 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Printed by U-Boot when probing the SPI-NOR flash, followed by the name of
+/// the chip and its geometry.
+pub static DETECTED: &str = "SF: Detected ";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    Unqualified(String),
+    WrongGeometry {
+        name: String,
+        expected: Geometry,
+        actual: Geometry,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unqualified(name) => write!(f, "Unqualified SPI-NOR flash {name}"),
+            Error::WrongGeometry {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "SPI-NOR flash {name} with wrong geometry ({actual}, expected {expected})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Geometry of a SPI-NOR flash in bytes.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Geometry {
+    pub page_size: u64,
+    pub erase_size: u64,
+    pub size: u64,
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "page size {}, erase size {}, total {}",
+            format_size(self.page_size),
+            format_size(self.erase_size),
+            format_size(self.size)
+        )
+    }
+}
+
+/// SPI-NOR flash as detected by U-Boot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NorFlash {
+    /// Name of the chip, e.g. "XM25QH64C".
+    pub name: String,
+    pub geometry: Geometry,
+}
+
+impl NorFlash {
+    /// Parses the line printed when U-Boot probes the flash, e.g.
+    /// `SF: Detected XM25QH64C with page size 256 Bytes, erase size 4 KiB,
+    /// total 8 MiB`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if something unexpected happens.
+    #[must_use]
+    pub fn parse(console_output: &str) -> Option<NorFlash> {
+        let re = Regex::new(&format!(
+            r"{}(\S+) with page size (\d+ \w+), erase size (\d+ \w+), total (\d+ \w+)",
+            regex::escape(DETECTED)
+        ))
+        .expect("Failed to create regular expression");
+
+        let c = re.captures(console_output)?;
+        Some(NorFlash {
+            name: c[1].to_string(),
+            geometry: Geometry {
+                page_size: parse_size(&c[2])?,
+                erase_size: parse_size(&c[3])?,
+                size: parse_size(&c[4])?,
+            },
+        })
+    }
+
+    /// Checks that the flash is one of the `qualified` parts and has their
+    /// geometry.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the flash is not qualified or differs in size.
+    pub fn validate(&self, qualified: &BTreeMap<String, Geometry>) -> Result<(), Error> {
+        let Some(&expected) = qualified.get(&self.name) else {
+            return Err(Error::Unqualified(self.name.clone()));
+        };
+        if self.geometry != expected {
+            return Err(Error::WrongGeometry {
+                name: self.name.clone(),
+                expected,
+                actual: self.geometry,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the flash as recorded in the diagnosis.
+    #[must_use]
+    pub fn values(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (String::from("nor_flash"), self.name.clone()),
+            (
+                String::from("nor_flash_page_size"),
+                format_size(self.geometry.page_size),
+            ),
+            (
+                String::from("nor_flash_erase_size"),
+                format_size(self.geometry.erase_size),
+            ),
+            (
+                String::from("nor_flash_size"),
+                format_size(self.geometry.size),
+            ),
+        ])
+    }
+}
+
+/// Parses a size as printed by U-Boot, e.g. "4 KiB".
+fn parse_size(size: &str) -> Option<u64> {
+    let (number, unit) = size.split_once(' ')?;
+    let shift = match unit {
+        "Bytes" => 0,
+        "KiB" => 10,
+        "MiB" => 20,
+        "GiB" => 30,
+        _ => return None,
+    };
+    number.parse::<u64>().ok().map(|n| n << shift)
+}
+
+fn format_size(size: u64) -> String {
+    match size {
+        0 => String::from("0 Bytes"),
+        s if s % (1 << 30) == 0 => format!("{} GiB", s >> 30),
+        s if s % (1 << 20) == 0 => format!("{} MiB", s >> 20),
+        s if s % (1 << 10) == 0 => format!("{} KiB", s >> 10),
+        s => format!("{s} Bytes"),
+    }
+}
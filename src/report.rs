@@ -0,0 +1,94 @@
+use crate::analyzer::Diagnosis;
+use crate::catalog::Catalog;
+use crate::config::Config;
+use crate::iprid;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Station setup a diagnosis was made with.
+#[derive(Serialize)]
+pub struct Station {
+    pub name: String,
+    pub serial_port: String,
+    pub power_control: bool,
+    pub invert_rts: bool,
+    pub catalog: Option<PathBuf>,
+    pub catalog_version: u32,
+    pub exhaustive: bool,
+}
+
+/// Machine-readable test report of one unit.
+#[derive(Serialize)]
+pub struct Report<'a> {
+    pub iprid: &'a str,
+    pub timestamp: DateTime<Utc>,
+    pub tool_version: &'static str,
+    pub station: Station,
+    #[serde(flatten)]
+    pub diagnosis: &'a Diagnosis,
+}
+
+impl<'a> Report<'a> {
+    #[must_use]
+    pub fn new(
+        iprid: &'a str,
+        config: &Config,
+        catalog: &Catalog,
+        diagnosis: &'a Diagnosis,
+    ) -> Report<'a> {
+        Report {
+            iprid,
+            timestamp: Utc::now(),
+            tool_version: env!("CARGO_PKG_VERSION"),
+            station: Station {
+                name: config.station.clone(),
+                serial_port: config.serial_port.clone(),
+                power_control: config.power_control,
+                invert_rts: config.invert_rts,
+                catalog: config.catalog.clone(),
+                catalog_version: catalog.version,
+                exhaustive: config.exhaustive,
+            },
+            diagnosis,
+        }
+    }
+
+    /// Writes the report as `<IPRID>_<timestamp>.json` into `dir`, which is
+    /// created if necessary. Existing reports are kept, a report of the same
+    /// unit and second is written as `<IPRID>_<timestamp>_<n>.json`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the report cannot be written.
+    pub fn save(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let stem = format!(
+            "{}_{}",
+            iprid::normalize(self.iprid),
+            self.timestamp.format("%Y%m%dT%H%M%SZ")
+        );
+        let mut n = 1;
+        let (path, file) = loop {
+            let file_name = if n == 1 {
+                format!("{stem}.json")
+            } else {
+                format!("{stem}_{n}.json")
+            };
+            let path = dir.join(file_name);
+            match File::options().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => n += 1,
+                Err(e) => return Err(e),
+            }
+        };
+
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(path)
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+/// Printed by the SPL whenever the DUT comes out of reset.
+pub static SPL_BANNER: &str = "U-Boot SPL ";
+
+/// Number of lines kept from before a reset.
+const LINES_BEFORE_RESET: usize = 3;
+
+/// Start of the DUT found in its console output, either the power on or a
+/// reset, e.g. by the watchdog or after an exception.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Start {
+    /// Last non-empty lines printed before, which usually tell why the DUT
+    /// was reset.
+    pub last_lines: Vec<String>,
+}
+
+/// Returns every start of the DUT in `console_output` in order.
+#[must_use]
+pub fn find_starts(console_output: &str) -> Vec<Start> {
+    let mut starts = Vec::new();
+    let mut last_lines: Vec<&str> = Vec::new();
+
+    for line in console_output.lines().map(str::trim) {
+        if let Some(i) = line.find(SPL_BANNER) {
+            // Garbage from the reset may precede the banner on the same line
+            let before = line[..i].trim();
+            if !before.is_empty() {
+                last_lines.push(before);
+            }
+            let skip = last_lines.len().saturating_sub(LINES_BEFORE_RESET);
+            starts.push(Start {
+                last_lines: last_lines.drain(..).skip(skip).map(String::from).collect(),
+            });
+        } else if !line.is_empty() {
+            last_lines.push(line);
+        }
+    }
+
+    starts
+}
+
+/// Returns the lines before each reset, separated by an empty line.
+#[must_use]
+pub fn excerpt(resets: &[Start]) -> String {
+    resets
+        .iter()
+        .map(|r| r.last_lines.join("\n"))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Returns the number of resets as recorded in the diagnosis.
+#[must_use]
+pub fn values(resets: &[Start]) -> BTreeMap<String, String> {
+    BTreeMap::from([(String::from("resets"), resets.len().to_string())])
+}
+
+/// Returns e.g. "3 resets".
+#[must_use]
+pub fn count(resets: &[Start]) -> String {
+    match resets.len() {
+        1 => String::from("1 reset"),
+        n => format!("{n} resets"),
+    }
+}
@@ -0,0 +1,237 @@
+use crate::transport::ConsoleTransport;
+use figment::{
+    providers::{Format, Toml},
+    Figment,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+const PROMPT: &str = "=> ";
+const KERNEL_START: &str = "## Booting kernel from FIT Image\nStarting kernel ...\n\n";
+
+/// Time U-Boot waits for a key press before booting the kernel.
+pub const AUTOBOOT_DELAY: Duration = Duration::from_secs(2);
+
+/// Time between two boots of a unit in a boot loop.
+pub const BOOT_LOOP_DELAY: Duration = Duration::from_secs(1);
+
+/// Time without input after which the unit is considered to be powered off.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Behaviour of the simulated gateway.
+///
+/// Test data files (`tests/data/*.toml`) are valid scenarios: the first chunk
+/// of `console_output` is printed on power on, every other chunk up to the
+/// next prompt answers one U-Boot command. The answer to `boot` is the kernel
+/// log, after that every line entered in Linux is answered by the next chunk
+/// including the shell prompt. Without any prompt in `console_output` autoboot
+/// cannot be stopped.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub console_output: Vec<String>,
+    /// Answers to specific U-Boot commands, used before `console_output`.
+    #[serde(default)]
+    pub commands: BTreeMap<String, String>,
+    /// Reset after printing the boot banner instead of offering a prompt.
+    #[serde(default)]
+    pub boot_loop: bool,
+}
+
+impl Scenario {
+    /// # Errors
+    ///
+    /// Will return `Err` if the scenario cannot be read or is malformed.
+    pub fn load(path: &Path) -> Result<Scenario, Box<figment::Error>> {
+        Ok(Figment::from(Toml::file_exact(path)).extract()?)
+    }
+
+    fn banner(&self) -> String {
+        self.console_output.first().cloned().unwrap_or_default()
+    }
+
+    fn has_prompt(&self) -> bool {
+        self.console_output
+            .iter()
+            .any(|chunk| chunk.trim() == PROMPT.trim())
+    }
+
+    fn answers(&self) -> VecDeque<String> {
+        self.console_output
+            .iter()
+            .skip(1)
+            .filter(|chunk| chunk.trim() != PROMPT.trim())
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    PoweredOff,
+    /// Waiting for a key press to stop autoboot.
+    Autoboot,
+    Shell,
+    /// Autoboot was not stopped.
+    Kernel,
+    BootLoop,
+}
+
+/// Gateway emulating the boot and U-Boot shell according to a [`Scenario`].
+///
+/// The unit is powered on by the first input, as the doctor starts sending
+/// right after switching the power on, the next input stops autoboot. Passing time is driven by the caller
+/// with [`Simulator::expire`].
+pub struct Simulator {
+    scenario: Scenario,
+    state: State,
+    line: String,
+    answers: VecDeque<String>,
+    output: VecDeque<u8>,
+}
+
+impl Simulator {
+    #[must_use]
+    pub fn new(scenario: Scenario) -> Simulator {
+        Simulator {
+            scenario,
+            state: State::PoweredOff,
+            line: String::new(),
+            answers: VecDeque::new(),
+            output: VecDeque::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Returns how long the unit stays in the current state without input.
+    #[must_use]
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.state {
+            State::PoweredOff => None,
+            State::Autoboot => Some(AUTOBOOT_DELAY),
+            State::Shell | State::Kernel => Some(IDLE_TIMEOUT),
+            State::BootLoop => Some(BOOT_LOOP_DELAY),
+        }
+    }
+
+    /// Handles `input` received on the console and returns the output.
+    pub fn input(&mut self, input: &[u8]) -> String {
+        match self.state {
+            State::PoweredOff => self.power_on(),
+            // Autoboot consumes all input while waiting for the key
+            State::Autoboot if self.scenario.has_prompt() => {
+                self.state = State::Shell;
+                String::from(PROMPT)
+            }
+            State::Shell | State::Kernel => input.iter().map(|&byte| self.shell(byte)).collect(),
+            State::Autoboot | State::BootLoop => String::new(),
+        }
+    }
+
+    /// Advances the unit once [`Simulator::timeout`] passed without input
+    /// and returns the output.
+    pub fn expire(&mut self) -> String {
+        match self.state {
+            State::Autoboot => {
+                self.state = State::Kernel;
+                String::from(KERNEL_START)
+            }
+            State::Shell | State::Kernel => {
+                self.state = State::PoweredOff;
+                String::new()
+            }
+            State::BootLoop => self.scenario.banner(),
+            State::PoweredOff => String::new(),
+        }
+    }
+
+    fn power_on(&mut self) -> String {
+        self.line.clear();
+        self.answers = self.scenario.answers();
+        self.state = if self.scenario.boot_loop {
+            State::BootLoop
+        } else {
+            State::Autoboot
+        };
+        self.scenario.banner()
+    }
+
+    fn shell(&mut self, byte: u8) -> String {
+        match byte {
+            b'\x03' if self.state == State::Shell => {
+                self.line.clear();
+                format!("<INTERRUPT>\n{PROMPT}")
+            }
+            b'\r' | b'\n' => {
+                let command = std::mem::take(&mut self.line);
+                match command.trim() {
+                    _ if self.state == State::Kernel => {
+                        format!("\n{}", self.answers.pop_front().unwrap_or_default())
+                    }
+                    "boot" => format!("\n{}", self.boot()),
+                    command => format!("\n{}{PROMPT}", self.run(command)),
+                }
+            }
+            b' '..=b'~' => {
+                self.line.push(char::from(byte));
+                char::from(byte).to_string()
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Leaves the shell, the kernel log is the next answer of the scenario.
+    fn boot(&mut self) -> String {
+        self.state = State::Kernel;
+        self.answers
+            .pop_front()
+            .unwrap_or_else(|| String::from(KERNEL_START))
+    }
+
+    fn run(&mut self, command: &str) -> String {
+        if command.is_empty() {
+            return String::new();
+        }
+
+        if let Some(answer) = self.scenario.commands.get(command) {
+            return answer.clone();
+        }
+
+        self.answers
+            .pop_front()
+            .unwrap_or_else(|| format!("Unknown command '{command}' - try 'help'\n"))
+    }
+}
+
+/// The simulator can stand in for a console, time only passes if
+/// [`Simulator::expire`] is called.
+impl ConsoleTransport for Simulator {
+    fn read(&mut self, buf: &mut [u8], _timeout: Duration) -> io::Result<usize> {
+        let len = self.output.len().min(buf.len());
+        for (dst, src) in buf.iter_mut().zip(self.output.drain(..len)) {
+            *dst = src;
+        }
+        Ok(len)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let output = self.input(buf);
+        self.output.extend(output.bytes());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_power_line(&mut self, _level: bool) -> io::Result<()> {
+        Ok(())
+    }
+}
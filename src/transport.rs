@@ -0,0 +1,251 @@
+use serialport::SerialPort;
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Console connection to the DUT.
+pub trait ConsoleTransport: Send {
+    /// Reads the available bytes into `buf`, waiting at most `timeout`.
+    ///
+    /// Returns `Ok(0)` if nothing was received in time.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the connection failed.
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize>;
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the connection failed.
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()>;
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the connection failed.
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// Sets the control line switching the DUT power.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the line cannot be set.
+    fn set_power_line(&mut self, level: bool) -> io::Result<()>;
+}
+
+/// Serial port of the jig, power is switched with the RTS line.
+pub struct SerialTransport {
+    port: Box<dyn SerialPort>,
+}
+
+impl SerialTransport {
+    /// # Errors
+    ///
+    /// Will return `Err` if serial port cannot be opened.
+    pub fn open(path: &str) -> Result<SerialTransport, serialport::Error> {
+        let port = serialport::new(path, 115_200)
+            .timeout(Duration::from_millis(100))
+            .open()?;
+        Ok(SerialTransport { port })
+    }
+}
+
+impl ConsoleTransport for SerialTransport {
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        if self.port.timeout() != timeout {
+            self.port.set_timeout(timeout)?;
+        }
+        match self.port.read(buf) {
+            Err(e) if e.kind() == ErrorKind::TimedOut => Ok(0),
+            result => result,
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.port.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+
+    fn set_power_line(&mut self, level: bool) -> io::Result<()> {
+        Ok(self.port.write_request_to_send(level)?)
+    }
+}
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+#[derive(Clone, Copy)]
+enum TelnetState {
+    Data,
+    Iac,
+    Option(u8),
+    Subnegotiation,
+    SubnegotiationIac,
+}
+
+/// Console server reachable over TCP, e.g. ser2net in raw or telnet mode.
+///
+/// Telnet commands are stripped from the received data and all option
+/// requests are refused. Power switching is not supported.
+pub struct TcpTransport {
+    stream: TcpStream,
+    state: TelnetState,
+}
+
+impl TcpTransport {
+    /// # Errors
+    ///
+    /// Will return `Err` if the console server cannot be reached.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<TcpTransport> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(TcpTransport {
+            stream,
+            state: TelnetState::Data,
+        })
+    }
+
+    /// Removes telnet commands from `buf` in place and returns the number of
+    /// data bytes left.
+    fn strip_telnet(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut replies = Vec::new();
+        let mut len = 0;
+
+        for i in 0..buf.len() {
+            let byte = buf[i];
+            self.state = match (self.state, byte) {
+                (TelnetState::Data, IAC) => TelnetState::Iac,
+                (TelnetState::Data, _) | (TelnetState::Iac, IAC) => {
+                    buf[len] = byte;
+                    len += 1;
+                    TelnetState::Data
+                }
+                (TelnetState::Iac, WILL | WONT | DO | DONT) => TelnetState::Option(byte),
+                (TelnetState::Iac, SB) => TelnetState::Subnegotiation,
+                (TelnetState::Option(command), option) => {
+                    match command {
+                        WILL => replies.extend([IAC, DONT, option]),
+                        DO => replies.extend([IAC, WONT, option]),
+                        _ => {}
+                    }
+                    TelnetState::Data
+                }
+                (TelnetState::Subnegotiation, IAC) => TelnetState::SubnegotiationIac,
+                (TelnetState::SubnegotiationIac, SE) | (TelnetState::Iac, _) => TelnetState::Data,
+                (TelnetState::Subnegotiation | TelnetState::SubnegotiationIac, _) => {
+                    TelnetState::Subnegotiation
+                }
+            };
+        }
+
+        if !replies.is_empty() {
+            self.stream.write_all(&replies)?;
+        }
+
+        Ok(len)
+    }
+}
+
+impl ConsoleTransport for TcpTransport {
+    fn read(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(timeout))?;
+        let bytes_read = match self.stream.read(buf) {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+                return Ok(0)
+            }
+            Err(e) => return Err(e),
+        };
+        self.strip_telnet(&mut buf[..bytes_read])
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.stream.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+
+    /// Console servers offer no control line, the DUT power has to be switched
+    /// externally.
+    fn set_power_line(&mut self, _level: bool) -> io::Result<()> {
+        Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "console server cannot switch the DUT power",
+        ))
+    }
+}
+
+/// In-memory transport replaying a fixed script, e.g. for tests and demos.
+///
+/// Every read returns the next chunk of the script, once the script is used
+/// up reads time out.
+#[derive(Default)]
+pub struct ScriptedTransport {
+    chunks: VecDeque<Vec<u8>>,
+    written: Vec<u8>,
+    power_line: Option<bool>,
+}
+
+impl ScriptedTransport {
+    pub fn new<I, T>(chunks: I) -> ScriptedTransport
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Vec<u8>>,
+    {
+        ScriptedTransport {
+            chunks: chunks.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns everything written to the transport.
+    #[must_use]
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Returns the last level of the power line, if it was set.
+    #[must_use]
+    pub fn power_line(&self) -> Option<bool> {
+        self.power_line
+    }
+}
+
+impl ConsoleTransport for ScriptedTransport {
+    fn read(&mut self, buf: &mut [u8], _timeout: Duration) -> io::Result<usize> {
+        let Some(mut chunk) = self.chunks.pop_front() else {
+            return Ok(0);
+        };
+        let len = chunk.len().min(buf.len());
+        buf[..len].copy_from_slice(&chunk[..len]);
+        if len < chunk.len() {
+            self.chunks.push_front(chunk.split_off(len));
+        }
+        Ok(len)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.written.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_power_line(&mut self, level: bool) -> io::Result<()> {
+        self.power_line = Some(level);
+        Ok(())
+    }
+}
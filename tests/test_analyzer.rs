@@ -1,110 +1,528 @@
+use chrono::Utc;
 use core::time::Duration;
-use mockall::mock;
 use rstest::rstest;
 use serde::Deserialize;
-use serialport::{ClearBuffer, DataBits, FlowControl, Parity, StopBits};
-use smart_garden_gateway_doctor::analyzer::analyze;
-
-mock! {
-    pub SerialPort {}
-    impl std::io::Read for SerialPort {
-        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-            Ok([]);
-        }
+use smart_garden_gateway_doctor::analyzer::{analyze, Diagnosis, Direction, Error, Outcome};
+use smart_garden_gateway_doctor::boot_timing::Milestone;
+use smart_garden_gateway_doctor::catalog::Catalog;
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::report::Report;
+use smart_garden_gateway_doctor::transport::{ConsoleTransport, ScriptedTransport};
+
+#[derive(Deserialize)]
+struct TestData {
+    console_output: Vec<String>,
+    message: String,
+    #[serde(default)]
+    failures: Vec<String>,
+}
+
+/// Console whose connection is lost, e.g. an unplugged USB-serial adapter.
+struct BrokenTransport;
+
+impl ConsoleTransport for BrokenTransport {
+    fn read(&mut self, _buf: &mut [u8], _timeout: Duration) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::BrokenPipe.into())
     }
-    impl serialport::SerialPort for SerialPort {
-        fn name(&self) -> Option<String>;
-        fn baud_rate(&self) -> serialport::Result<u32>;
-        fn data_bits(&self) -> serialport::Result<DataBits>;
-        fn flow_control(&self) -> serialport::Result<FlowControl>;
-        fn parity(&self) -> serialport::Result<Parity>;
-        fn stop_bits(&self) -> serialport::Result<StopBits>;
-        fn timeout(&self) -> Duration;
-        fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()>;
-        fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()>;
-        fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()>;
-        fn set_parity(&mut self, parity: Parity) -> serialport::Result<()>;
-        fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()>;
-        fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()>;
-        fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()>;
-        fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()>;
-        fn read_clear_to_send(&mut self) -> serialport::Result<bool>;
-        fn read_data_set_ready(&mut self) -> serialport::Result<bool>;
-        fn read_ring_indicator(&mut self) -> serialport::Result<bool>;
-        fn read_carrier_detect(&mut self) -> serialport::Result<bool>;
-        fn bytes_to_read(&self) -> serialport::Result<u32>;
-        fn bytes_to_write(&self) -> serialport::Result<u32>;
-        fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()>;
-        fn try_clone(&self) -> serialport::Result<Box<dyn serialport::SerialPort>>;
-        fn set_break(&self) -> serialport::Result<()>;
-        fn clear_break(&self) -> serialport::Result<()>;
+
+    fn write_all(&mut self, _buf: &[u8]) -> std::io::Result<()> {
+        Ok(())
     }
-    impl std::io::Write for SerialPort {
-        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>;
-        fn flush(&mut self) -> std::io::Result<()>;
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
     }
-}
 
-fn zero() -> usize {
-    0
+    fn set_power_line(&mut self, _level: bool) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
-#[derive(Deserialize, Clone)]
-struct TestData {
-    console_output: Vec<String>,
-    #[serde(default = "zero")]
-    index: usize,
-    message: String,
-}
+/// Console of a DUT which never stops printing `line`, e.g. in a boot loop.
+struct ChattyTransport(&'static [u8]);
+
+impl ConsoleTransport for ChattyTransport {
+    fn read(&mut self, buf: &mut [u8], _timeout: Duration) -> std::io::Result<usize> {
+        std::thread::sleep(Duration::from_millis(10));
+        buf[..self.0.len()].copy_from_slice(self.0);
+        Ok(self.0.len())
+    }
+
+    fn write_all(&mut self, _buf: &[u8]) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 
-impl TestData {
-    #[allow(clippy::unnecessary_wraps)]
-    pub fn read_console_output(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        if self.index >= self.console_output.len() {
-            return Ok(0);
-        }
-        let s = &self.console_output[self.index];
-        buf[..s.len()].copy_from_slice(s.as_bytes());
-        self.index += 1;
-        Ok(s.len())
+    fn set_power_line(&mut self, _level: bool) -> std::io::Result<()> {
+        Ok(())
     }
 }
 
+fn analyze_case(case: &str, config: &Config) -> (TestData, Diagnosis) {
+    let file_path = std::path::PathBuf::from(format!(
+        "{}/tests/data/{case}.toml",
+        env!("CARGO_MANIFEST_DIR")
+    ));
+    let file_content = std::fs::read_to_string(&file_path)
+        .unwrap_or_else(|_| panic!("Failed to read {}", &file_path.display()));
+    let test_data: TestData = toml::from_str(file_content.as_str())
+        .unwrap_or_else(|_| panic!("Failed to parse test data {}", &file_path.display()));
+    let mut console = ScriptedTransport::new(test_data.console_output.clone());
+
+    let diagnosis = analyze(&mut console, &Catalog::default(), config, Some(Utc::now()))
+        .expect("Failed to analyze");
+
+    (test_data, diagnosis)
+}
+
 #[rstest]
 #[test_log::test]
 fn test_analyze(
     #[values(
+        "boot_loop",
         "button_stuck",
+        "factory_data_bad_crc",
+        "factory_data_prompt_in_dump",
+        "nand_worn",
         "no_fdata",
+        "no_iprid",
         "no_issues",
         "no_nand",
+        "no_nand_button_stuck",
         "no_phy",
         "no_u-boot_prompt",
         "no_u-boot",
+        "nor_flash_unqualified",
+        "u-boot_env_bad_crc",
+        "u-boot_env_inconsistent_flags",
+        "u-boot_env_wrong_bootcmd",
+        "wrong_mtd_layout",
         "wrong_ram_size"
     )]
     case: &str,
 ) {
-    let file_path = std::path::PathBuf::from(format!(
-        "{}/tests/data/{case}.toml",
-        env!("CARGO_MANIFEST_DIR")
-    ));
-    let file_content = std::fs::read_to_string(&file_path)
-        .unwrap_or_else(|_| panic!("Failed to read {}", &file_path.display()));
-    let test_data: TestData = toml::from_str(file_content.as_str())
-        .unwrap_or_else(|_| panic!("Failed to parse test data {}", &file_path.display()));
-    let mut serial_port = Box::new(MockSerialPort::new());
-
-    serial_port.expect_write().returning(|buf| Ok(buf.len()));
-    serial_port.expect_flush().returning(|| Ok(()));
-    serial_port.expect_read().returning({
-        let mut t = test_data.clone();
-        move |buf| t.read_console_output(buf)
-    });
-
-    let diagnosis = analyze(&mut (serial_port as Box<dyn serialport::SerialPort>));
+    let (test_data, diagnosis) = analyze_case(case, &Config::default());
 
     let message = test_data.message.as_str();
 
     assert_eq!(diagnosis.message, message);
 }
+
+#[rstest]
+#[test_log::test]
+fn test_analyze_exhaustive(
+    #[values("factory_data_prompt_in_dump", "no_nand_button_stuck")] case: &str,
+) {
+    let config = Config {
+        exhaustive: true,
+        ..Default::default()
+    };
+    let (test_data, diagnosis) = analyze_case(case, &config);
+
+    let failures: Vec<&str> = diagnosis.failures().map(|r| r.id.as_str()).collect();
+
+    assert_eq!(diagnosis.message, test_data.message);
+    assert_eq!(failures, test_data.failures);
+    assert!(diagnosis
+        .results
+        .iter()
+        .all(|r| r.outcome != Outcome::Skipped));
+}
+
+#[rstest]
+#[test_log::test]
+fn test_analyze_linux(
+    #[values(
+        "linux_env_bad_crc",
+        "linux_kernel_panic",
+        "linux_no_issues",
+        "linux_no_login",
+        "linux_ubi_error",
+        "linux_watchdog_reset"
+    )]
+    case: &str,
+) {
+    let config = Config {
+        linux: true,
+        linux_timeout: 1,
+        ..Default::default()
+    };
+    let (test_data, diagnosis) = analyze_case(case, &config);
+
+    assert_eq!(diagnosis.message, test_data.message);
+    assert!(diagnosis
+        .transcript
+        .iter()
+        .any(|e| e.direction == Direction::Tx && e.text == "boot\n"));
+}
+
+#[test_log::test]
+fn test_analyze_linux_password_not_recorded() {
+    let config = Config {
+        linux: true,
+        linux_timeout: 1,
+        linux_password: String::from("secret"),
+        ..Default::default()
+    };
+    let (test_data, diagnosis) = analyze_case("linux_password", &config);
+    let report = Report::new("5d8c3b7e", &config, &Catalog::default(), &diagnosis);
+    let json = serde_json::to_string(&report).expect("Failed to serialize report");
+
+    assert_eq!(diagnosis.message, test_data.message);
+    assert!(diagnosis
+        .transcript
+        .iter()
+        .any(|e| e.direction == Direction::Tx && e.text == "***\n"));
+    assert!(!json.contains("secret"));
+}
+
+#[test_log::test]
+fn test_analyze_login_times_out() {
+    let config = Config {
+        linux: true,
+        linux_timeout: 1,
+        login_timeout: 0,
+        ..Default::default()
+    };
+    let catalog = Catalog::from_toml("version = 1\ncheck = []").expect("Invalid catalog");
+    let mut console = ScriptedTransport::new([
+        "U-Boot SPL 2021.04-gardena-6\n",
+        "=> ",
+        "Starting kernel ...\n\ngardena-gateway login: ",
+    ]);
+
+    let start = std::time::Instant::now();
+    let diagnosis = analyze(&mut console, &catalog, &config, None).expect("Failed to analyze");
+
+    assert_eq!(diagnosis.message, "Timed out in phase linux");
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[rstest]
+#[test_log::test]
+fn test_analyze_network(
+    #[values("network_no_issues", "network_no_link", "network_ping_failed")] case: &str,
+) {
+    let config = Config {
+        network: true,
+        ..Default::default()
+    };
+    let (test_data, diagnosis) = analyze_case(case, &config);
+
+    assert_eq!(diagnosis.message, test_data.message);
+}
+
+#[test_log::test]
+fn test_analyze_network_fills_in_addresses() {
+    let config = Config {
+        network: true,
+        station_ip: String::from("192.168.1.1"),
+        dut_ip: String::from("192.168.1.2"),
+        ..Default::default()
+    };
+    let (_, diagnosis) = analyze_case("network_no_issues", &config);
+
+    let ping = diagnosis
+        .results
+        .iter()
+        .find(|r| r.id == "ping")
+        .expect("Ping not run");
+
+    assert_eq!(ping.values["pinged_ip"], "192.168.1.1");
+    assert!(diagnosis
+        .transcript
+        .iter()
+        .any(|e| e.direction == Direction::Tx
+            && e.text == "setenv ipaddr 192.168.1.2; ping 192.168.1.1\n"));
+}
+
+#[test_log::test]
+fn test_analyze_u_boot_command_not_filled_in() {
+    let catalog = Catalog::from_toml(
+        r#"
+        version = 1
+        [[check]]
+        id = "echo"
+        phase = "u-boot"
+        command = "echo {dut_ip}"
+        expected = "{dut_ip}"
+        message = "Wrong echo"
+        instructions = ""
+        "#,
+    )
+    .expect("Invalid catalog");
+    let config = Config {
+        network: true,
+        ..Default::default()
+    };
+    let mut console =
+        ScriptedTransport::new(["U-Boot SPL 2021.04-gardena-6\n", "=> ", "{dut_ip}\n", "=> "]);
+
+    let diagnosis = analyze(&mut console, &catalog, &config, None).expect("Failed to analyze");
+
+    assert_eq!(diagnosis.message, "No issues found");
+    assert!(diagnosis
+        .transcript
+        .iter()
+        .any(|e| e.direction == Direction::Tx && e.text == "echo {dut_ip}\n"));
+}
+
+#[test_log::test]
+fn test_analyze_without_network() {
+    let (_, diagnosis) = analyze_case("network_no_link", &Config::default());
+
+    assert_eq!(diagnosis.message, "No issues found");
+    assert!(diagnosis.results.iter().all(|r| r.id != "phy-link"));
+}
+
+#[test_log::test]
+fn test_analyze_without_linux() {
+    let (_, diagnosis) = analyze_case("linux_kernel_panic", &Config::default());
+
+    assert_eq!(diagnosis.message, "No issues found");
+    assert!(diagnosis.results.iter().all(|r| r.id != "kernel-panic"));
+}
+
+#[test_log::test]
+fn test_analyze_skips_after_failure() {
+    let (_, diagnosis) = analyze_case("no_nand_button_stuck", &Config::default());
+
+    let button = diagnosis
+        .results
+        .iter()
+        .find(|r| r.id == "button")
+        .expect("Missing button check result");
+
+    assert_eq!(button.outcome, Outcome::Skipped);
+    assert_eq!(diagnosis.failures().count(), 1);
+}
+
+#[test_log::test]
+fn test_analyze_captures_values() {
+    let (_, diagnosis) = analyze_case("no_issues", &Config::default());
+
+    let values: std::collections::BTreeMap<&str, &str> = diagnosis
+        .results
+        .iter()
+        .flat_map(|r| r.values.iter())
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+
+    assert_eq!(values["ram_size"], "128 MiB");
+    assert_eq!(values["cpu_clock_mhz"], "580");
+    assert_eq!(values["nor_flash"], "XM25QH64C");
+    assert_eq!(values["u_boot_version"], "2021.04-gardena-6");
+    assert_eq!(values["u_boot_build_date"], "Jun 10 2021 - 16:05:31 +0000");
+    assert!(diagnosis
+        .transcript
+        .iter()
+        .any(|e| e.direction == Direction::Rx && e.text.contains("DRAM:  128 MiB")));
+    assert_eq!(
+        diagnosis.boot_profile.nor_flash.as_deref(),
+        Some("XM25QH64C")
+    );
+}
+
+#[test_log::test]
+fn test_analyze_measures_boot_timing() {
+    let (_, diagnosis) = analyze_case("no_issues", &Config::default());
+
+    let milestones: Vec<Milestone> = diagnosis.boot_timing.milestones.into_keys().collect();
+
+    assert_eq!(
+        milestones,
+        [
+            Milestone::FirstByte,
+            Milestone::Spl,
+            Milestone::UBoot,
+            Milestone::Dram,
+            Milestone::Prompt
+        ]
+    );
+    assert!(diagnosis.results.iter().all(|r| !r.id.starts_with("slow-")));
+}
+
+#[test_log::test]
+fn test_analyze_records_rejected_value() {
+    let (_, diagnosis) = analyze_case("wrong_ram_size", &Config::default());
+
+    let ram_size = diagnosis
+        .results
+        .iter()
+        .find(|r| r.id == "ram-size")
+        .expect("Missing RAM size check result");
+
+    assert_eq!(ram_size.outcome, Outcome::Fail);
+    assert_eq!(ram_size.values["ram_size"], "2.9 GiB");
+    assert_eq!(ram_size.excerpt.as_deref(), Some("DRAM:  2.9 GiB"));
+}
+
+#[rstest]
+#[case("5d8c3b7e-2f41-4a96-8c0e-9b1f7a2d6e34")]
+#[case("5d8c3b7e'2f41'4a96'8c0e'9b1f7a2d6e34")]
+#[test_log::test]
+fn test_verify_iprid(#[case] scanned: &str) {
+    let (_, mut diagnosis) = analyze_case("no_issues", &Config::default());
+
+    diagnosis.verify_iprid(scanned);
+
+    assert!(diagnosis.healthy);
+    assert_eq!(diagnosis.message, "No issues found");
+}
+
+#[test_log::test]
+fn test_verify_iprid_mismatch() {
+    let (_, mut diagnosis) = analyze_case("button_stuck", &Config::default());
+
+    diagnosis.verify_iprid("01234567-89ab-cdef-0123-456789abcdef");
+
+    // Button stuck is found before the IPRID is read
+    assert_eq!(diagnosis.message, "Button stuck");
+
+    let (_, mut diagnosis) = analyze_case("no_issues", &Config::default());
+
+    diagnosis.verify_iprid("01234567-89ab-cdef-0123-456789abcdef");
+
+    let failures: Vec<&str> = diagnosis.failures().map(|r| r.id.as_str()).collect();
+    assert!(!diagnosis.healthy);
+    assert_eq!(diagnosis.message, "Scanned IPRID does not match the unit");
+    assert_eq!(failures, ["iprid-match"]);
+    assert_eq!(
+        diagnosis.results[0].values["unit_iprid"],
+        "5d8c3b7e-2f41-4a96-8c0e-9b1f7a2d6e34"
+    );
+}
+
+#[test_log::test]
+fn test_verify_iprid_factory_data_mismatch() {
+    let (_, mut diagnosis) = analyze_case("no_issues", &Config::default());
+    let factory_data = diagnosis
+        .results
+        .iter_mut()
+        .find(|r| r.id == "factory-data-content")
+        .expect("Missing factory data check result");
+    factory_data.values.insert(
+        String::from("iprid"),
+        String::from("01234567-89ab-cdef-0123-456789abcdef"),
+    );
+
+    diagnosis.verify_iprid("5d8c3b7e-2f41-4a96-8c0e-9b1f7a2d6e34");
+
+    assert!(!diagnosis.healthy);
+    assert_eq!(diagnosis.message, "Scanned IPRID does not match the unit");
+    assert_eq!(
+        diagnosis.results[0].excerpt.as_deref(),
+        Some("scanned 5d8c3b7e-2f41-4a96-8c0e-9b1f7a2d6e34, factory data 01234567-89ab-cdef-0123-456789abcdef")
+    );
+}
+
+#[test_log::test]
+fn test_analyze_times_out() {
+    let config = Config {
+        u_boot_timeout: 1,
+        ..Config::default()
+    };
+    let mut console = ChattyTransport(b"SF: Timeout!\n");
+
+    let diagnosis =
+        analyze(&mut console, &Catalog::default(), &config, None).expect("Failed to analyze");
+
+    assert_eq!(diagnosis.message, "Timed out in phase early");
+    assert_eq!(diagnosis.results[0].id, "early-timeout");
+    assert_eq!(
+        diagnosis.results[0].excerpt.as_deref(),
+        Some("SF: Timeout!")
+    );
+}
+
+#[test_log::test]
+fn test_analyze_endless_boot_loop() {
+    let config = Config {
+        u_boot_timeout: 1,
+        ..Config::default()
+    };
+    let mut console = ChattyTransport(b"U-Boot SPL 2021.04-gardena-6\nTrying to boot from NOR\n");
+
+    let diagnosis =
+        analyze(&mut console, &Catalog::default(), &config, None).expect("Failed to analyze");
+
+    let resets: usize = diagnosis.results[0].values["resets"]
+        .parse()
+        .expect("Invalid reset count");
+    assert!(resets > 1);
+    assert_eq!(diagnosis.message, format!("Boot loop ({resets} resets)"));
+    assert_eq!(diagnosis.results[0].id, "early-reset");
+}
+
+#[rstest]
+#[case::reset_during_command("reset_during_command", "nand")]
+#[case::unexpected_reset("unexpected_reset", "early-reset")]
+#[test_log::test]
+fn test_analyze_reset(#[case] case: &str, #[case] id: &str) {
+    let (test_data, diagnosis) = analyze_case(case, &Config::default());
+
+    let reset = diagnosis.failures().next().expect("Reset not detected");
+    assert_eq!(diagnosis.message, test_data.message);
+    assert_eq!(reset.id, id);
+    assert_eq!(reset.values["resets"], "1");
+    assert!(reset
+        .excerpt
+        .as_deref()
+        .is_some_and(|e| e.ends_with("### ERROR ### Please RESET the board ###")));
+}
+
+#[test_log::test]
+fn test_analyze_command_times_out() {
+    let config = Config {
+        command_timeout: 1,
+        ..Config::default()
+    };
+    let (test_data, _) = analyze_case("no_issues", &Config::default());
+    let mut console_output = test_data.console_output[..2].to_vec();
+    console_output.push(String::from("List of MTD devices:\n* nor0\n"));
+    let mut console = ScriptedTransport::new(console_output);
+
+    let diagnosis =
+        analyze(&mut console, &Catalog::default(), &config, None).expect("Failed to analyze");
+
+    let nand = diagnosis
+        .results
+        .iter()
+        .find(|r| r.id == "nand")
+        .expect("Missing NAND check result");
+    assert_eq!(nand.outcome, Outcome::Fail);
+    assert_eq!(
+        nand.message,
+        "Timed out in phase u-boot running \"mtd list\""
+    );
+    assert_eq!(nand.excerpt.as_deref(), Some("* nor0"));
+}
+
+#[test_log::test]
+fn test_analyze_keys_echoed_after_prompt() {
+    let (test_data, _) = analyze_case("no_issues", &Config::default());
+    let mut console_output = test_data.console_output.clone();
+    console_output[1] = String::from("=> xx");
+    let mut console = ScriptedTransport::new(console_output);
+
+    let diagnosis = analyze(&mut console, &Catalog::default(), &Config::default(), None)
+        .expect("Failed to analyze");
+
+    assert_eq!(diagnosis.message, test_data.message);
+    assert!(diagnosis.healthy);
+}
+
+#[test_log::test]
+fn test_analyze_station_fault() {
+    let result = analyze(
+        &mut BrokenTransport,
+        &Catalog::default(),
+        &Config::default(),
+        None,
+    );
+
+    assert!(matches!(result, Err(Error::Read(_))));
+}
@@ -0,0 +1,61 @@
+use smart_garden_gateway_doctor::boot_profile::BootProfile;
+
+static BANNER: &str = "
+U-Boot SPL 2021.04-gardena-6 (Jun 10 2021 - 16:05:31 +0000)
+Trying to boot from NOR
+
+
+U-Boot 2021.04-gardena-6 (Jun 10 2021 - 16:05:31 +0000)
+
+CPU:   MediaTek MT7688A ver:1 eco:2
+Boot:  DDR2, SPI-NOR 3-Byte Addr, CPU clock from XTAL
+Clock: CPU: 580MHz, Bus: 193MHz, XTAL: 40MHz
+Model: GARDENA smart Gateway (MT7688)
+DRAM:  128 MiB
+WDT:   Started with servicing (60s timeout)
+Loading Environment from SPIFlash... SF: Detected XM25QH64C with page size 256 Bytes, erase size 4 KiB, total 8 MiB
+OK
+F-Data:factory-data version 1 detected
+Net:   eth0: eth@10110000
+Autoboot in 2 seconds
+";
+
+#[test]
+fn test_parse_banner() {
+    let profile = BootProfile::parse(BANNER);
+
+    assert_eq!(
+        profile,
+        BootProfile {
+            spl_version: Some(String::from("2021.04-gardena-6")),
+            u_boot_version: Some(String::from("2021.04-gardena-6")),
+            u_boot_build_date: Some(String::from("Jun 10 2021 - 16:05:31 +0000")),
+            cpu: Some(String::from("MediaTek MT7688A ver:1 eco:2")),
+            boot_mode: Some(String::from(
+                "DDR2, SPI-NOR 3-Byte Addr, CPU clock from XTAL"
+            )),
+            cpu_clock_mhz: Some(580),
+            bus_clock_mhz: Some(193),
+            xtal_mhz: Some(40),
+            model: Some(String::from("GARDENA smart Gateway (MT7688)")),
+            ram_size: Some(String::from("128 MiB")),
+            nor_flash: Some(String::from("XM25QH64C")),
+        }
+    );
+}
+
+#[test]
+fn test_parse_empty() {
+    assert_eq!(BootProfile::parse(""), BootProfile::default());
+}
+
+#[test]
+fn test_parse_spl_only() {
+    let profile = BootProfile::parse(
+        "U-Boot SPL 2021.04-gardena-6 (Jun 10 2021 - 16:05:31 +0000)\nTrying to boot from NOR\nSPL: failed to boot from all boot devices\n",
+    );
+
+    assert_eq!(profile.spl_version.as_deref(), Some("2021.04-gardena-6"));
+    assert_eq!(profile.u_boot_version, None);
+    assert_eq!(profile.model, None);
+}
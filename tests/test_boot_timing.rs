@@ -0,0 +1,106 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use smart_garden_gateway_doctor::analyzer::{Direction, TranscriptEntry};
+use smart_garden_gateway_doctor::boot_timing::{BootTiming, Milestone, SlowMilestone};
+use std::collections::BTreeMap;
+
+fn entry(
+    powered_on: DateTime<Utc>,
+    elapsed_ms: i64,
+    direction: Direction,
+    text: &str,
+) -> TranscriptEntry {
+    TranscriptEntry {
+        time: powered_on + TimeDelta::milliseconds(elapsed_ms),
+        direction,
+        text: String::from(text),
+    }
+}
+
+fn transcript(powered_on: DateTime<Utc>) -> Vec<TranscriptEntry> {
+    vec![
+        entry(powered_on, 0, Direction::Tx, "x"),
+        entry(
+            powered_on,
+            40,
+            Direction::Rx,
+            "\nU-Boot SPL 2021.04-gardena-6 (Jun 10 2021",
+        ),
+        entry(
+            powered_on,
+            45,
+            Direction::Rx,
+            " - 16:05:31 +0000)\nTrying to boot from NOR\n",
+        ),
+        entry(
+            powered_on,
+            310,
+            Direction::Rx,
+            "\n\nU-Boot 2021.04-gardena-6 (Jun 10 2021 - 16:05:31 +0000)\n",
+        ),
+        entry(powered_on, 520, Direction::Rx, "DRAM:  128 MiB\n"),
+        entry(powered_on, 1900, Direction::Rx, "=> "),
+        entry(powered_on, 2500, Direction::Tx, "boot\n"),
+        entry(powered_on, 5600, Direction::Rx, "Starting kernel ...\n"),
+        entry(
+            powered_on,
+            5700,
+            Direction::Rx,
+            "[    0.000000] Linux version 5.15.107\n",
+        ),
+    ]
+}
+
+#[test]
+fn test_measure() {
+    let powered_on = Utc::now();
+
+    let timing = BootTiming::measure(Some(powered_on), &transcript(powered_on));
+
+    assert_eq!(
+        timing.milestones,
+        BTreeMap::from([
+            (Milestone::FirstByte, 40),
+            (Milestone::Spl, 40),
+            (Milestone::UBoot, 310),
+            (Milestone::Dram, 520),
+            (Milestone::Prompt, 1900),
+            (Milestone::KernelStart, 3100),
+            (Milestone::LinuxVersion, 3200),
+        ])
+    );
+}
+
+#[test]
+fn test_measure_without_power_on() {
+    let powered_on = Utc::now();
+
+    let timing = BootTiming::measure(None, &transcript(powered_on));
+
+    assert_eq!(
+        timing.milestones,
+        BTreeMap::from([
+            (Milestone::KernelStart, 3100),
+            (Milestone::LinuxVersion, 3200)
+        ])
+    );
+}
+
+#[test]
+fn test_slow() {
+    let powered_on = Utc::now();
+    let timing = BootTiming::measure(Some(powered_on), &transcript(powered_on));
+    let limits = BTreeMap::from([
+        (Milestone::Dram, 500),
+        (Milestone::Prompt, 5000),
+        (Milestone::Login, 1000),
+    ]);
+
+    assert_eq!(
+        timing.slow(&limits),
+        [SlowMilestone {
+            milestone: Milestone::Dram,
+            elapsed_ms: 520,
+            limit_ms: 500
+        }]
+    );
+}
@@ -0,0 +1,158 @@
+use rstest::rstest;
+use smart_garden_gateway_doctor::analyzer::{analyze, Direction};
+use smart_garden_gateway_doctor::capture::{
+    Capture, Data, Error, RecordingTransport, ReplayTransport,
+};
+use smart_garden_gateway_doctor::catalog::Catalog;
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::transport::ScriptedTransport;
+use std::path::PathBuf;
+
+#[rstest]
+#[test_log::test]
+fn test_replay_capture(#[files("tests/captures/*.toml")] path: PathBuf) {
+    let capture = Capture::load(&path)
+        .unwrap_or_else(|e| panic!("Failed to load capture {}: {e}", path.display()));
+    let message = capture.message.clone();
+    let (catalog, config) = capture.setup();
+    let mut console = ReplayTransport::new(capture);
+
+    let diagnosis = analyze(&mut console, &catalog, &config, None).expect("Failed to analyze");
+
+    assert_eq!(diagnosis.message, message);
+}
+
+#[test_log::test]
+fn test_record_and_replay() {
+    let path = std::env::temp_dir().join(format!("sggd-capture-{}.toml", std::process::id()));
+    let mut scripted = ScriptedTransport::new([
+        &b"U-Boot SPL 2021.04-gardena-6\n\xff\n"[..],
+        b"=>",
+        b"\nList of MTD devices:\n",
+        b"=> ",
+    ]);
+
+    let mut recorder = RecordingTransport::new(&mut scripted);
+    let original = analyze(&mut recorder, &Catalog::default(), &Config::default(), None)
+        .expect("Failed to analyze");
+    let mut capture = recorder.into_capture();
+    capture.message.clone_from(&original.message);
+    capture.save(&path).expect("Failed to save capture");
+
+    let loaded = Capture::load(&path).expect("Failed to load capture");
+    std::fs::remove_file(&path).expect("Failed to remove capture");
+    let mut console = ReplayTransport::new(loaded.clone());
+    let replayed = analyze(&mut console, &Catalog::default(), &Config::default(), None)
+        .expect("Failed to analyze");
+
+    assert_eq!(loaded.message, original.message);
+    assert_eq!(replayed.message, original.message);
+    assert_eq!(loaded.events[0].direction, Direction::Tx);
+    assert_eq!(
+        loaded.events[1].data,
+        Data::Bytes(b"U-Boot SPL 2021.04-gardena-6\n\xff\n".to_vec())
+    );
+    assert!(loaded
+        .events
+        .iter()
+        .any(|e| e.direction == Direction::Rx && e.data == Data::Text(String::from("=>"))));
+}
+
+#[test_log::test]
+fn test_replay_with_recorded_setup() {
+    let catalog = Catalog::from_toml(
+        r#"
+        version = 1
+        [[check]]
+        id = "version"
+        phase = "u-boot"
+        command = "version"
+        expected = "U-Boot 2021.04"
+        message = "Wrong U-Boot version"
+        instructions = ""
+        "#,
+    )
+    .expect("Invalid catalog");
+    let config = Config {
+        linux_password: String::from("secret"),
+        ..Default::default()
+    };
+    let mut scripted = ScriptedTransport::new([
+        "U-Boot SPL 2021.04-gardena-6\n",
+        "=> ",
+        "U-Boot 2021.10\n",
+        "=> ",
+    ]);
+    let mut recorder = RecordingTransport::new(&mut scripted);
+    let original = analyze(&mut recorder, &catalog, &config, None).expect("Failed to analyze");
+    let mut capture = recorder.into_capture();
+    capture.message.clone_from(&original.message);
+    capture.set_setup(&catalog, &config);
+
+    let content = toml::to_string(&capture).expect("Failed to serialize capture");
+    let loaded: Capture = toml::from_str(&content).expect("Failed to parse capture");
+    let (catalog, config) = loaded.setup();
+    let mut console = ReplayTransport::new(loaded);
+    let replayed = analyze(&mut console, &catalog, &config, None).expect("Failed to analyze");
+
+    assert_eq!(original.message, "Wrong U-Boot version");
+    assert_eq!(replayed.message, original.message);
+    assert!(!content.contains("secret"));
+}
+
+#[test_log::test]
+fn test_capture_without_linux_password() {
+    let config = Config {
+        linux: true,
+        linux_timeout: 1,
+        linux_password: String::from("secret"),
+        ..Default::default()
+    };
+    let mut scripted = ScriptedTransport::new([
+        "U-Boot SPL 2021.04-gardena-6\n",
+        "=> ",
+        "Starting kernel ...\n\ngardena-gateway login: ",
+        "\nPassword: ",
+        "\n\nroot@gardena-gateway:~# ",
+    ]);
+    let catalog = Catalog::from_toml("version = 1\ncheck = []").expect("Invalid catalog");
+    let mut recorder = RecordingTransport::new(&mut scripted);
+    analyze(&mut recorder, &catalog, &config, None).expect("Failed to analyze");
+    let mut capture = recorder.into_capture();
+    capture.set_setup(&catalog, &config);
+
+    let content = toml::to_string(&capture).expect("Failed to serialize capture");
+
+    assert!(!content.contains("secret"));
+    assert!(content.contains("***"));
+}
+
+#[test_log::test]
+fn test_load_invalid_catalog() {
+    let path =
+        std::env::temp_dir().join(format!("sggd-capture-invalid-{}.toml", std::process::id()));
+    let catalog = Catalog::from_toml(
+        r#"
+        version = 1
+        [[check]]
+        id = "version"
+        phase = "u-boot"
+        command = "version"
+        expected = "U-Boot 2021.04"
+        message = "Wrong U-Boot version"
+        instructions = ""
+        "#,
+    )
+    .expect("Invalid catalog");
+    let mut capture = Capture::default();
+    capture.set_setup(&catalog, &Config::default());
+    let content = toml::to_string(&capture)
+        .expect("Failed to serialize capture")
+        .replace("command = \"version\"\n", "");
+    std::fs::write(&path, content).expect("Failed to write capture");
+
+    let result = Capture::load(&path);
+    std::fs::remove_file(&path).expect("Failed to remove capture");
+
+    assert!(matches!(result, Err(Error::Catalog(_))));
+}
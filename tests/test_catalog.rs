@@ -0,0 +1,205 @@
+use rstest::rstest;
+use smart_garden_gateway_doctor::catalog::{Catalog, Error, Phase, ValueLimits};
+
+#[test]
+fn test_default_catalog() {
+    let catalog = Catalog::default();
+
+    assert!(catalog.validate().is_ok());
+    assert_eq!(catalog.phase(Phase::Early).count(), 11);
+    assert_eq!(catalog.phase(Phase::UBoot).count(), 7);
+    assert_eq!(catalog.phase(Phase::Network).count(), 2);
+    assert_eq!(catalog.phase(Phase::Kernel).count(), 6);
+    assert_eq!(catalog.phase(Phase::Linux).count(), 5);
+}
+
+#[rstest]
+#[case::unsupported_version(
+    "
+    version = 2
+    check = []
+    ",
+    "UnsupportedVersion(2)"
+)]
+#[case::duplicate_id(
+    r#"
+    version = 1
+    [[check]]
+    id = "ram"
+    phase = "early"
+    expected = "DRAM:  128 MiB"
+    message = "Wrong RAM size detected"
+    instructions = ""
+    [[check]]
+    id = "ram"
+    phase = "early"
+    expected = "DRAM:  128 MiB"
+    message = "Wrong RAM size detected"
+    instructions = ""
+    "#,
+    "DuplicateId(\"ram\")"
+)]
+#[case::missing_pattern(
+    r#"
+    version = 1
+    [[check]]
+    id = "ram"
+    phase = "early"
+    message = "Wrong RAM size detected"
+    instructions = ""
+    "#,
+    "MissingPattern(\"ram\")"
+)]
+#[case::missing_command(
+    r#"
+    version = 1
+    [[check]]
+    id = "nand"
+    phase = "u-boot"
+    expected = "spi-nand0"
+    message = "NAND flash not detected"
+    instructions = ""
+    "#,
+    "MissingCommand(\"nand\")"
+)]
+#[case::unexpected_command(
+    r#"
+    version = 1
+    [[check]]
+    id = "ram"
+    phase = "early"
+    command = "bdinfo"
+    expected = "DRAM:  128 MiB"
+    message = "Wrong RAM size detected"
+    instructions = ""
+    "#,
+    "UnexpectedCommand(\"ram\")"
+)]
+#[case::unexpected_kernel_command(
+    r#"
+    version = 1
+    [[check]]
+    id = "panic"
+    phase = "kernel"
+    command = "boot"
+    not_expected = "Kernel panic"
+    message = "Kernel panic"
+    instructions = ""
+    "#,
+    "UnexpectedCommand(\"panic\")"
+)]
+#[case::unexpected_decoder(
+    r#"
+    version = 1
+    [[check]]
+    id = "factory-data"
+    phase = "linux"
+    command = "cat /dev/mtd2"
+    decode = "factory-data"
+    message = "Factory data could not be read"
+    instructions = ""
+    "#,
+    "UnexpectedDecoder(\"factory-data\")"
+)]
+#[case::invalid_regex(
+    r#"
+    version = 1
+    [[check]]
+    id = "ram"
+    phase = "early"
+    regex = true
+    expected = 'DRAM:\s+(\d+'
+    message = "Wrong RAM size detected"
+    instructions = ""
+    "#,
+    "InvalidRegex"
+)]
+#[case::unknown_capture(
+    r#"
+    version = 1
+    [[check]]
+    id = "ram"
+    phase = "early"
+    regex = true
+    expected = 'DRAM:\s+(?P<ram_mib>\d+) MiB'
+    message = "Wrong RAM size detected"
+    instructions = ""
+    [check.values.ram_size]
+    min = 128
+    "#,
+    "UnknownCapture(\"ram\", \"ram_size\")"
+)]
+#[case::values_without_regex(
+    r#"
+    version = 1
+    [[check]]
+    id = "ram"
+    phase = "early"
+    expected = 'DRAM:\s+(?P<ram_mib>\d+) MiB'
+    message = "Wrong RAM size detected"
+    instructions = ""
+    [check.values.ram_mib]
+    min = 128
+    "#,
+    "UnknownCapture(\"ram\", \"ram_mib\")"
+)]
+fn test_invalid_catalog(#[case] toml: &str, #[case] error: &str) {
+    let result = Catalog::from_toml(toml);
+
+    assert!(format!("{:?}", result.unwrap_err()).starts_with(error));
+}
+
+#[rstest]
+#[case::unexpected_command(
+    Error::UnexpectedCommand(String::from("panic")),
+    "Check \"panic\" must not have a \"command\", early and kernel checks run none"
+)]
+#[case::missing_command(
+    Error::MissingCommand(String::from("ping")),
+    "Check \"ping\" has no \"command\", U-Boot and network checks run one"
+)]
+fn test_command_error_message(#[case] error: Error, #[case] message: &str) {
+    assert_eq!(error.to_string(), message);
+}
+
+#[test]
+fn test_unknown_phase() {
+    let toml = r#"
+    version = 1
+    [[check]]
+    id = "recovery"
+    phase = "recovery"
+    expected = "login:"
+    message = "Recovery system did not boot"
+    instructions = ""
+    "#;
+
+    assert!(matches!(Catalog::from_toml(toml), Err(Error::Parse(_))));
+}
+
+#[rstest]
+#[case::in_range("580", true)]
+#[case::too_low("400", false)]
+#[case::too_high("600", false)]
+#[case::not_a_number("fast", false)]
+fn test_value_range(#[case] value: &str, #[case] accepted: bool) {
+    let limits = ValueLimits {
+        min: Some(575.0),
+        max: Some(585.0),
+        ..Default::default()
+    };
+
+    assert_eq!(limits.accepts(value), accepted);
+}
+
+#[rstest]
+#[case::allowed("XM25QH64C", true)]
+#[case::not_allowed("W25Q128", false)]
+fn test_value_allow_list(#[case] value: &str, #[case] accepted: bool) {
+    let limits = ValueLimits {
+        allowed: Some(vec![String::from("XM25QH64C"), String::from("W25Q64")]),
+        ..Default::default()
+    };
+
+    assert_eq!(limits.accepts(value), accepted);
+}
@@ -0,0 +1,91 @@
+use smart_garden_gateway_doctor::config::{Config, Error};
+use std::path::PathBuf;
+
+fn config_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sggd-config-{name}-{}.toml", std::process::id()));
+    std::fs::write(&path, content).expect("Failed to write config file");
+    path
+}
+
+#[test]
+fn test_load() {
+    let path = config_file("load", "station = \"bench-1\"\nlinux = true\n");
+
+    let config = Config::load(&path).expect("Failed to load config");
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(config.station, "bench-1");
+    assert!(config.linux);
+    assert_eq!(config.command_timeout, Config::default().command_timeout);
+}
+
+#[test]
+fn test_load_malformed() {
+    let path = config_file("malformed", "linux = \"maybe\"\n");
+
+    let result = Config::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(result, Err(Error::Load(_))));
+}
+
+#[test]
+fn test_load_replaces_tables() {
+    let path = config_file(
+        "tables",
+        r#"
+[mtd_layout.nor0]
+uboot = { offset = 0x0, size = 0xa0000 }
+
+[qualified_nor_flashes.W25Q64JV]
+page_size = 256
+erase_size = 0x1000
+size = 0x800000
+
+[required_env]
+bootcmd = "run boot_slot"
+"#,
+    );
+
+    let config = Config::load(&path).expect("Failed to load config");
+    std::fs::remove_file(&path).unwrap();
+
+    let layout = config.mtd_layout();
+    assert_eq!(layout.keys().collect::<Vec<_>>(), ["nor0"]);
+    assert_eq!(layout["nor0"].keys().collect::<Vec<_>>(), ["uboot"]);
+    assert_eq!(
+        config.qualified_nor_flashes().keys().collect::<Vec<_>>(),
+        ["W25Q64JV"]
+    );
+    assert_eq!(
+        config.required_env().keys().collect::<Vec<_>>(),
+        ["bootcmd"]
+    );
+}
+
+#[test]
+fn test_load_default_tables() {
+    let path = config_file("default-tables", "station = \"bench-1\"\n");
+
+    let config = Config::load(&path).expect("Failed to load config");
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(config.mtd_layout(), Config::default().mtd_layout());
+    assert!(config.mtd_layout()["nor0"].contains_key("unused"));
+    assert!(config.qualified_nor_flashes().contains_key("XM25QH64C"));
+    assert!(config.required_env().contains_key("bootlimit"));
+}
+
+#[test]
+fn test_load_invalid_required_env() {
+    let path = config_file("invalid-env", "[required_env]\nbootcmd = \"run (\"\n");
+
+    let result = Config::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let Err(error) = result else {
+        panic!("Invalid pattern accepted");
+    };
+    assert!(matches!(error, Error::Load(_)));
+    assert!(error.to_string().contains("required_env.bootcmd"));
+}
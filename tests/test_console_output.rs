@@ -0,0 +1,110 @@
+use chrono::{TimeDelta, Utc};
+use rstest::rstest;
+use smart_garden_gateway_doctor::console_output::{ConsoleOutput, LineMatcher};
+use std::collections::BTreeMap;
+
+fn console_output(chunks: &[&[u8]]) -> ConsoleOutput {
+    let mut console_output = ConsoleOutput::default();
+    for chunk in chunks {
+        console_output.push(chunk, Utc::now());
+    }
+    console_output
+}
+
+#[rstest]
+#[case::split_line(&[b"DRAM:  12".as_slice(), b"8 MiB\r\n".as_slice()], "DRAM:  128 MiB\n")]
+#[case::split_character(&[b"\xc2".as_slice(), b"\xb5s\n".as_slice()], "\u{b5}s\n")]
+#[case::split_escape_sequence(&[b"\x1b[1".as_slice(), b";32mOK\x1b".as_slice(), b"[0m\n".as_slice()], "OK\n")]
+#[case::control_characters(&[b"\0\x07=>\x08 ".as_slice()], "=> ")]
+#[case::invalid_utf8(&[b"\xff\xfeU-Boot SPL 2021.04\n".as_slice()], "U-Boot SPL 2021.04\n")]
+fn test_push(#[case] chunks: &[&[u8]], #[case] text: &str) {
+    assert_eq!(console_output(chunks).text(), text);
+}
+
+#[test]
+fn test_push_returns_added_text() {
+    let mut console_output = ConsoleOutput::default();
+
+    assert_eq!(
+        console_output.push(b"Net:   eth0\x1b[", Utc::now()),
+        "Net:   eth0"
+    );
+    assert_eq!(console_output.push(b"0m\r\n=> ", Utc::now()), "\n=> ");
+}
+
+#[test]
+fn test_lines() {
+    let start = Utc::now();
+    let mut console_output = ConsoleOutput::default();
+
+    console_output.push(b"U-Boot 2021.04\r\nDR", start);
+    console_output.push(b"AM:  128 MiB\n\n=> ", start + TimeDelta::milliseconds(200));
+
+    let lines: Vec<(&str, i64)> = console_output
+        .lines()
+        .iter()
+        .map(|l| (l.text.as_str(), (l.time - start).num_milliseconds()))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            ("U-Boot 2021.04", 0),
+            ("DRAM:  128 MiB", 0),
+            ("", 200),
+            ("=> ", 200)
+        ]
+    );
+}
+
+#[test]
+fn test_line_matcher() {
+    let console_output =
+        console_output(&[b"SF: Detected XM", b"25QH64C with page size 256 Bytes\n"]);
+    let matcher =
+        LineMatcher::new(r"SF: Detected (?P<nor_flash>\S+)", true).expect("Invalid regex");
+
+    let found = matcher
+        .find(console_output.lines())
+        .expect("Line not found");
+
+    assert_eq!(
+        found.line.text,
+        "SF: Detected XM25QH64C with page size 256 Bytes"
+    );
+    assert_eq!(
+        found.values,
+        BTreeMap::from([(String::from("nor_flash"), String::from("XM25QH64C"))])
+    );
+}
+
+#[rstest]
+#[case::plain_string("value is 1", false, true)]
+#[case::plain_string_is_no_regex("value is .", false, false)]
+#[case::across_lines("value is 1\\s+Net", true, false)]
+fn test_line_matcher_matches(#[case] pattern: &str, #[case] regex: bool, #[case] found: bool) {
+    let console_output = console_output(&[b"gpio: pin PA11 (gpio 11) value is 1\nNet:   eth0\n"]);
+    let matcher = LineMatcher::new(pattern, regex).expect("Invalid regex");
+
+    assert_eq!(matcher.find(console_output.lines()).is_some(), found);
+}
+
+#[test]
+fn test_split_off_and_append() {
+    let mut console_output = console_output(&[b"value is 1\n=> ver\n=> ".as_slice()]);
+
+    let rest = console_output.split_off(14);
+
+    assert_eq!(console_output.text(), "value is 1\n=> ");
+    assert_eq!(rest.text(), "ver\n=> ");
+    let lines: Vec<&str> = rest.lines().iter().map(|l| l.text.as_str()).collect();
+    assert_eq!(lines, ["ver", "=> "]);
+
+    console_output.append(rest);
+
+    let lines: Vec<&str> = console_output
+        .lines()
+        .iter()
+        .map(|l| l.text.as_str())
+        .collect();
+    assert_eq!(lines, ["value is 1", "=> ver", "=> "]);
+}
@@ -0,0 +1,111 @@
+use rstest::rstest;
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::environment::{Environment, Error, RedundantFlags, ValuePattern};
+use std::collections::BTreeMap;
+
+static PRINTENV: &str = "\
+baudrate=115200
+bootcmd=run boot_slot
+bootlimit=3
+ethaddr=28:6d:cd:5c:3e:11
+stdin=serial
+
+Environment size: 94/65532 bytes
+=> ";
+
+#[test]
+fn test_parse() {
+    let env = Environment::parse(PRINTENV).expect("No variables found");
+
+    assert_eq!(env.variables.len(), 5);
+    assert_eq!(env.variables["bootcmd"], "run boot_slot");
+    assert_eq!(env.values()["env.ethaddr"], "28:6d:cd:5c:3e:11");
+    assert_eq!(env.validate(&Config::default().required_env()), Ok(()));
+}
+
+#[test]
+fn test_parse_without_variables() {
+    assert_eq!(
+        Environment::parse("Unknown command 'printenv' - try 'help'\n=> "),
+        None
+    );
+}
+
+#[test]
+fn test_validate_missing() {
+    let env =
+        Environment::parse(&PRINTENV.replace("bootlimit=3\n", "")).expect("No variables found");
+
+    let error = env
+        .validate(&Config::default().required_env())
+        .expect_err("Missing variable accepted");
+
+    assert_eq!(error, Error::Missing(String::from("bootlimit")));
+    assert_eq!(error.to_string(), "U-Boot variable bootlimit missing");
+}
+
+#[test]
+fn test_validate_unexpected_value() {
+    let env = Environment::parse(&PRINTENV.replace("28:6d:cd:5c:3e:11", "28:6d:cd:5c:3e"))
+        .expect("No variables found");
+
+    let error = env
+        .validate(&Config::default().required_env())
+        .expect_err("Unexpected value accepted");
+
+    assert_eq!(
+        error.to_string(),
+        "Unexpected U-Boot variable ethaddr=28:6d:cd:5c:3e"
+    );
+}
+
+#[test]
+fn test_validate_whole_value() {
+    let env = Environment::parse(PRINTENV).expect("No variables found");
+    let pattern = ValuePattern::try_from(String::from("run")).expect("Invalid pattern");
+    let required = BTreeMap::from([(String::from("bootcmd"), pattern)]);
+
+    assert_eq!(
+        env.validate(&required),
+        Err(Error::UnexpectedValue {
+            name: String::from("bootcmd"),
+            value: String::from("run boot_slot")
+        })
+    );
+}
+
+#[rstest]
+#[case("01", "00")]
+#[case("00", "01")]
+fn test_redundant_flags(#[case] first: &str, #[case] second: &str) {
+    let dump = format!(
+        "84000000: 5a 3c 91 e7 {first} 61 72 63 68 3d 6d 69 70 73 00 62    Z<...arch=mips.b\n\
+         84000010: 0b 8f 2e 46 {second} 61 72 63 68 3d 6d 69 70 73 00 62    ...F.arch=mips.b\n=> "
+    );
+
+    let flags = RedundantFlags::parse(&dump).expect("No flags found");
+
+    assert_eq!(flags.validate(), Ok(()));
+    assert_eq!(
+        flags.values()["env_flags.uboot_env1"],
+        format!("0x{second}")
+    );
+}
+
+#[rstest]
+#[case([0x01, 0x01])]
+#[case([0x01, 0xff])]
+fn test_redundant_flags_inconsistent(#[case] flags: [u8; 2]) {
+    assert_eq!(
+        RedundantFlags(flags).validate(),
+        Err(Error::InconsistentFlags(flags))
+    );
+}
+
+#[test]
+fn test_redundant_flags_incomplete_dump() {
+    assert_eq!(
+        RedundantFlags::parse("84000000: 5a 3c 91 e7 01 61 72 63\n=> "),
+        None
+    );
+}
@@ -0,0 +1,155 @@
+use rstest::rstest;
+use smart_garden_gateway_doctor::analyzer::{U_BOOT_COMMAND_PROMPT, U_BOOT_PROMPT};
+use smart_garden_gateway_doctor::expect::{self, Expect};
+use smart_garden_gateway_doctor::simulator::{Scenario, Simulator};
+use smart_garden_gateway_doctor::transport::ScriptedTransport;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+fn deadline() -> Instant {
+    Instant::now() + Duration::from_millis(500)
+}
+
+#[test]
+fn test_expect_leaves_rest_of_output() {
+    let mut console = ScriptedTransport::new(["gpio: pin PA11 (gpio 11) value is 1\n=> ver\n=> "]);
+    let mut session = Expect::new(&mut console, expect::regex("=> ?"));
+
+    let first = session.wait_prompt(deadline()).expect("Failed to expect");
+    let second = session.wait_prompt(deadline()).expect("Failed to expect");
+
+    assert_eq!(first.matched, Some(0));
+    assert_eq!(
+        first.output.text(),
+        "gpio: pin PA11 (gpio 11) value is 1\n=> "
+    );
+    assert_eq!(second.output.text(), "ver\n=> ");
+}
+
+#[test]
+fn test_expect_earliest_pattern() {
+    let mut console = ScriptedTransport::new(["Ooops:\n", "U-Boot SPL 2021.04\n", "=> "]);
+    let mut session = Expect::new(&mut console, expect::regex("=> ?"));
+
+    let result = session
+        .expect(
+            &[&expect::regex("=> ?"), &expect::literal("U-Boot SPL")],
+            deadline(),
+        )
+        .expect("Failed to expect");
+
+    assert_eq!(result.matched, Some(1));
+    assert_eq!(result.output.text(), "Ooops:\nU-Boot SPL");
+}
+
+#[test]
+fn test_expect_deadline() {
+    let mut console = ScriptedTransport::new(["List of MTD devices:\n", "* nor0\n"]);
+    let mut session = Expect::new(&mut console, expect::regex("=> ?"));
+
+    let result = session.wait_prompt(deadline()).expect("Failed to expect");
+
+    assert_eq!(result.matched, None);
+    assert_eq!(result.output.text(), "List of MTD devices:\n* nor0\n");
+}
+
+#[rstest]
+#[case::echo(&["mtd li", "st\r\n* nor0\r\n=> "], "* nor0\n=> ")]
+#[case::no_echo(&["\n* nor0\n=> "], "\n* nor0\n=> ")]
+fn test_send_line_drops_echo(#[case] chunks: &[&str], #[case] output: &str) {
+    let mut console = ScriptedTransport::new(chunks.iter().copied());
+    let mut session = Expect::new(&mut console, expect::regex("=> ?"));
+
+    session.send_line("mtd list").expect("Failed to send");
+    let result = session.wait_prompt(deadline()).expect("Failed to expect");
+
+    assert_eq!(result.output.text(), output);
+}
+
+#[test]
+fn test_ignore_asynchronous_messages() {
+    let mut console = ScriptedTransport::new([
+        "cat /proc/mtd\ndev:    size   erasesize  name\n[   12.3456",
+        "78] random: crng init done\nmtd0: 00800000 00001000 \"nor0\"\nroot@gateway:~# ",
+    ]);
+    let mut session = Expect::new(&mut console, expect::regex(r"(?m)^[^\s#$]+[#$] "));
+    session.ignore(expect::regex(r"\[\s*\d+\.\d+\] [^\n]*\n"));
+
+    session.send_line("cat /proc/mtd").expect("Failed to send");
+    let result = session.wait_prompt(deadline()).expect("Failed to expect");
+
+    assert_eq!(
+        result.output.text(),
+        "dev:    size   erasesize  name\nmtd0: 00800000 00001000 \"nor0\"\nroot@gateway:~# "
+    );
+    assert_eq!(result.output.lines().len(), 3);
+}
+
+#[test]
+fn test_ignore_skips_empty_matches() {
+    let mut console = ScriptedTransport::new(["ls\n[    1.000000] eth0: link up\nbin\n# "]);
+    let mut session = Expect::new(&mut console, expect::regex("# $"));
+    session.ignore(expect::regex(r"(\[\s*\d+\.\d+\] [^\n]*\n)?"));
+
+    session.send_line("ls").expect("Failed to send");
+    let result = session.wait_prompt(deadline()).expect("Failed to expect");
+
+    assert_eq!(result.output.text(), "bin\n# ");
+}
+
+#[test]
+fn test_interrupt_simulator() {
+    let scenario = Scenario {
+        console_output: vec![String::from("Autoboot in 2 seconds\n"), String::from("=>")],
+        commands: BTreeMap::from([(String::from("version"), String::from("U-Boot 2021.04\n"))]),
+        ..Default::default()
+    };
+    let mut simulator = Simulator::new(scenario);
+    let mut session = Expect::new(&mut simulator, expect::regex(U_BOOT_PROMPT));
+
+    let autoboot = session
+        .wait_prompt_sending(b"x", Duration::from_secs(1), deadline())
+        .expect("Failed to stop autoboot");
+    session.set_prompt(expect::regex(U_BOOT_COMMAND_PROMPT));
+    // Keys typed after autoboot stopped are echoed by the shell
+    session.send(b"xx").expect("Failed to send");
+    session.interrupt().expect("Failed to interrupt");
+    session.send_line("version").expect("Failed to send");
+    let version = session.wait_prompt(deadline()).expect("Failed to expect");
+
+    assert_eq!(autoboot.matched, Some(0));
+    assert_eq!(version.output.text(), "U-Boot 2021.04\n=> ");
+}
+
+#[test]
+fn test_interrupt_unconfirmed() {
+    let mut console = ScriptedTransport::new(["=> xx", "\nU-Boot 2021.04\n", "=> "]);
+    let mut session = Expect::new(&mut console, expect::regex(U_BOOT_PROMPT));
+
+    let autoboot = session.wait_prompt(deadline()).expect("Failed to expect");
+    session.set_prompt(expect::regex(U_BOOT_COMMAND_PROMPT));
+    session.interrupt().expect("Failed to interrupt");
+    let version = session.wait_prompt(deadline()).expect("Failed to expect");
+
+    assert_eq!(autoboot.matched, Some(0));
+    assert_eq!(version.output.text(), "xx\nU-Boot 2021.04\n=> ");
+}
+
+#[rstest]
+#[case::prompt_in_output(
+    &["84000010: 3d 3e ff ff ff ff ff ff    =>......\n", "=> "],
+    "84000010: 3d 3e ff ff ff ff ff ff    =>......\n=> "
+)]
+#[case::no_newline(
+    &["gpio: pin PA11 (gpio 11) value is 0", "=> "],
+    "gpio: pin PA11 (gpio 11) value is 0=> "
+)]
+fn test_u_boot_command_prompt(#[case] chunks: &[&str], #[case] output: &str) {
+    let mut console = ScriptedTransport::new(chunks.iter().copied());
+    let mut session = Expect::new(&mut console, expect::regex(U_BOOT_COMMAND_PROMPT));
+
+    let result = session.wait_prompt(deadline()).expect("Failed to expect");
+
+    assert_eq!(result.matched, Some(0));
+    assert_eq!(result.output.text(), output);
+}
@@ -0,0 +1,88 @@
+use rstest::rstest;
+use smart_garden_gateway_doctor::factory_data::{self, Error, FactoryData, MacAddress, SIZE};
+
+const IPRID: &str = "5d8c3b7e-2f41-4a96-8c0e-9b1f7a2d6e34";
+const WIFI_MAC: [u8; 6] = [0x28, 0x6d, 0xcd, 0x5c, 0x3e, 0x10];
+const ETH_MAC: [u8; 6] = [0x28, 0x6d, 0xcd, 0x5c, 0x3e, 0x11];
+
+/// Returns factory data as written in production, with a valid CRC.
+fn factory_data(version: u32, wifi_mac: [u8; 6], eth_mac: [u8; 6], iprid: &str) -> Vec<u8> {
+    let mut bytes = vec![0xff; SIZE];
+    bytes[0x04..0x0a].copy_from_slice(&wifi_mac);
+    bytes[0x28..0x2e].copy_from_slice(&eth_mac);
+    bytes[0x144..0x148].copy_from_slice(&0xcafe_babe_u32.to_le_bytes());
+    bytes[0x148..0x14c].copy_from_slice(&version.to_le_bytes());
+    bytes[0x14c..0x14c + iprid.len()].copy_from_slice(iprid.as_bytes());
+    let crc = crc32fast::hash(&bytes[0x144..]);
+    bytes[0x140..0x144].copy_from_slice(&crc.to_le_bytes());
+    bytes
+}
+
+#[test]
+fn test_decode() {
+    let bytes = factory_data(1, WIFI_MAC, ETH_MAC, IPRID);
+
+    let data = FactoryData::decode(&bytes).expect("Failed to decode factory data");
+
+    assert_eq!(data.version, 1);
+    assert_eq!(data.iprid, IPRID);
+    assert_eq!(data.hqv_id, "");
+    assert_eq!(data.wifi_mac.to_string(), "28:6d:cd:5c:3e:10");
+    assert_eq!(data.eth_mac, MacAddress(ETH_MAC));
+    assert_eq!(data.validate(), Ok(()));
+    assert_eq!(data.values()["iprid"], IPRID);
+}
+
+#[rstest]
+#[case::incomplete(factory_data(1, WIFI_MAC, ETH_MAC, IPRID)[..0x100].to_vec(), Error::Incomplete(0x100))]
+#[case::erased(vec![0xff; SIZE], Error::InvalidMagic(0xffff_ffff))]
+#[case::unknown_version(
+    factory_data(7, WIFI_MAC, ETH_MAC, IPRID),
+    Error::UnsupportedVersion(7)
+)]
+fn test_decode_invalid(#[case] bytes: Vec<u8>, #[case] error: Error) {
+    assert_eq!(FactoryData::decode(&bytes), Err(error));
+}
+
+#[test]
+fn test_decode_invalid_crc() {
+    let mut bytes = factory_data(1, WIFI_MAC, ETH_MAC, IPRID);
+    bytes[0x14c] = b'6';
+
+    let error = FactoryData::decode(&bytes).expect_err("Corrupt factory data decoded");
+
+    assert!(matches!(error, Error::InvalidCrc { stored, computed } if stored != computed));
+    assert!(error.to_string().starts_with("Factory data corrupt"));
+}
+
+#[rstest]
+#[case::zero_wifi_mac([0; 6], ETH_MAC, IPRID, Error::MissingMac("WiFi"))]
+#[case::erased_eth_mac(WIFI_MAC, [0xff; 6], IPRID, Error::MissingMac("Ethernet"))]
+#[case::duplicate_mac(ETH_MAC, ETH_MAC, IPRID, Error::DuplicateMac(MacAddress(ETH_MAC)))]
+#[case::invalid_iprid(WIFI_MAC, ETH_MAC, "", Error::InvalidIprid(String::new()))]
+fn test_validate(
+    #[case] wifi_mac: [u8; 6],
+    #[case] eth_mac: [u8; 6],
+    #[case] iprid: &str,
+    #[case] error: Error,
+) {
+    let data = FactoryData::decode(&factory_data(1, wifi_mac, eth_mac, iprid))
+        .expect("Failed to decode factory data");
+
+    assert_eq!(data.validate(), Err(error));
+}
+
+#[test]
+fn test_parse_dump() {
+    let console_output = "\
+md.b 0x84000000 0x20
+84000000: 28 76 01 00 28 6d cd 5c 3e 10 ff ff ff ff ff ff    (v..(m.\\>.......
+84000010: 35 64 20 61 62 20 63 64                            5d ab cd
+=> ";
+
+    let bytes = factory_data::parse_dump(console_output);
+
+    assert_eq!(bytes.len(), 24);
+    assert_eq!(&bytes[..4], [0x28, 0x76, 0x01, 0x00]);
+    assert_eq!(&bytes[16..], b"5d ab cd");
+}
@@ -0,0 +1,75 @@
+use smart_garden_gateway_doctor::analyzer::Diagnosis;
+use smart_garden_gateway_doctor::catalog::Catalog;
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::history::History;
+use smart_garden_gateway_doctor::report::Report;
+
+static IPRID: &str = "0123abcd-0000-1111-2222-333344445555";
+
+fn diagnosis(message: &str, healthy: bool) -> Diagnosis {
+    Diagnosis {
+        message: String::from(message),
+        healthy,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_history() {
+    let history = History::open_in_memory().expect("Failed to open history");
+    let config = Config {
+        station: String::from("bench-1"),
+        ..Default::default()
+    };
+    let catalog = Catalog::default();
+
+    assert!(history.entries(IPRID).unwrap().is_empty());
+
+    let first = diagnosis("Button stuck", false);
+    history
+        .record(&Report::new(IPRID, &config, &catalog, &first))
+        .unwrap();
+    let second = diagnosis("No issues found", true);
+    history
+        .record(&Report::new(IPRID, &config, &catalog, &second))
+        .unwrap();
+    let other = diagnosis("No issues found", true);
+    history
+        .record(&Report::new(
+            "ffffffff-0000-1111-2222-333344445555",
+            &config,
+            &catalog,
+            &other,
+        ))
+        .unwrap();
+
+    let entries = history.entries(IPRID).unwrap();
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].message, "No issues found");
+    assert!(entries[0].healthy);
+    assert_eq!(entries[1].message, "Button stuck");
+    assert!(!entries[1].healthy);
+    assert_eq!(entries[1].station, "bench-1");
+    assert!(entries.iter().all(|e| e.iprid == IPRID));
+}
+
+#[test]
+fn test_history_normalizes_iprid() {
+    let history = History::open_in_memory().expect("Failed to open history");
+    let scanned = "0123ABCD'0000'1111'2222'333344445555";
+
+    history
+        .record(&Report::new(
+            scanned,
+            &Config::default(),
+            &Catalog::default(),
+            &diagnosis("Button stuck", false),
+        ))
+        .unwrap();
+
+    let entries = history.entries(IPRID).unwrap();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].iprid, IPRID);
+}
@@ -0,0 +1,109 @@
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::mtd::{self, Deviation, Partition, Range};
+
+static MTD_LIST: &str = "\
+List of MTD devices:
+* nor0
+  - type: NOR flash
+  - block size: 0x1000 bytes
+  - min I/O: 0x1 bytes
+  - 0x000000000000-0x000000800000 : \"nor0\"
+\t  - 0x000000000000-0x0000000a0000 : \"uboot\"
+\t  - 0x0000000a0000-0x0000000b0000 : \"uboot_env0\"
+\t  - 0x0000000b0000-0x0000000c0000 : \"uboot_env1\"
+\t  - 0x0000000c0000-0x0000000d0000 : \"factory\"
+\t  - 0x0000000d0000-0x000000800000 : \"unused\"
+* spi-nand0
+  - device: spi-nand@1
+  - parent: spi@b00
+  - driver: spi_nand
+  - type: NAND flash
+  - block size: 0x20000 bytes
+  - min I/O: 0x800 bytes
+  - OOB size: 128 bytes
+  - OOB available: 63 bytes
+  - 0x000000000000-0x000008000000 : \"spi-nand0\"
+\t  - 0x000000000000-0x000008000000 : \"nand\"
+=> ";
+
+#[test]
+fn test_parse_list() {
+    let devices = mtd::parse_list(MTD_LIST);
+
+    assert_eq!(devices.len(), 2);
+    assert_eq!(devices[0].name, "nor0");
+    assert_eq!(devices[0].kind, "NOR flash");
+    assert_eq!(devices[0].block_size, Some(0x1000));
+    assert_eq!(devices[0].partitions.len(), 5);
+    assert_eq!(
+        devices[0].partitions[1],
+        Partition {
+            name: String::from("uboot_env0"),
+            range: Range {
+                offset: 0xa_0000,
+                size: 0x1_0000
+            }
+        }
+    );
+    assert_eq!(devices[1].name, "spi-nand0");
+    assert_eq!(devices[1].kind, "NAND flash");
+    assert_eq!(devices[1].block_size, Some(0x2_0000));
+    assert_eq!(
+        mtd::values(&devices)["mtd.spi-nand0.nand"],
+        "0x00000000-0x08000000"
+    );
+}
+
+#[test]
+fn test_parse_list_without_devices() {
+    assert!(mtd::parse_list("Could not find a valid device for spi0.1\n=> ").is_empty());
+}
+
+#[test]
+fn test_compare() {
+    let devices = mtd::parse_list(MTD_LIST);
+
+    assert_eq!(mtd::compare(&devices, &Config::default().mtd_layout()), []);
+}
+
+#[test]
+fn test_compare_deviations() {
+    let console_output = MTD_LIST
+        .replace("0x0000000a0000 : \"uboot\"", "0x0000000b0000 : \"uboot\"")
+        .replace("\"uboot_env1\"", "\"uboot_env\"")
+        .replace("* spi-nand0", "* spi-nand1");
+    let devices = mtd::parse_list(&console_output);
+
+    let deviations = mtd::compare(&devices, &Config::default().mtd_layout());
+
+    assert_eq!(
+        deviations,
+        [
+            Deviation::ResizedPartition {
+                device: String::from("nor0"),
+                name: String::from("uboot"),
+                expected: Range {
+                    offset: 0,
+                    size: 0xa_0000
+                },
+                actual: Range {
+                    offset: 0,
+                    size: 0xb_0000
+                },
+            },
+            Deviation::MissingPartition {
+                device: String::from("nor0"),
+                name: String::from("uboot_env1"),
+            },
+            Deviation::ExtraPartition {
+                device: String::from("nor0"),
+                name: String::from("uboot_env"),
+            },
+            Deviation::MissingDevice(String::from("spi-nand0")),
+        ]
+    );
+    assert_eq!(
+        deviations[0].to_string(),
+        "partition \"uboot\" on nor0 at 0x00000000-0x000b0000 instead of 0x00000000-0x000a0000"
+    );
+}
@@ -0,0 +1,52 @@
+use smart_garden_gateway_doctor::nand::{BadBlocks, Error};
+
+#[test]
+fn test_parse() {
+    let console_output = "\
+MTD device spi-nand0 bad blocks list:
+\t0x00060000
+\t0x03f60000
+=> ";
+
+    let bad_blocks = BadBlocks::parse(console_output).expect("Bad block list not found");
+
+    assert_eq!(bad_blocks.offsets, [0x0006_0000, 0x03f6_0000]);
+    assert_eq!(bad_blocks.values()["bad_blocks"], "2");
+    assert_eq!(
+        bad_blocks.values()["bad_block_offsets"],
+        "0x00060000 0x03f60000"
+    );
+}
+
+#[test]
+fn test_parse_without_bad_blocks() {
+    let bad_blocks = BadBlocks::parse("MTD device spi-nand0 bad blocks list:\n=> ")
+        .expect("Bad block list not found");
+
+    assert!(bad_blocks.offsets.is_empty());
+    assert_eq!(bad_blocks.validate(0), Ok(()));
+}
+
+#[test]
+fn test_parse_missing_list() {
+    assert_eq!(
+        BadBlocks::parse("MTD device spi-nand0 not found, ret -19\n=> "),
+        None
+    );
+}
+
+#[test]
+fn test_validate() {
+    let bad_blocks = BadBlocks {
+        offsets: vec![0x0006_0000, 0x0008_0000, 0x03f6_0000],
+    };
+
+    let error = bad_blocks.validate(2).expect_err("Worn NAND accepted");
+
+    assert_eq!(error, Error::TooManyBadBlocks { count: 3, max: 2 });
+    assert_eq!(
+        error.to_string(),
+        "NAND worn, too many bad blocks (3, at most 2)"
+    );
+    assert_eq!(bad_blocks.validate(3), Ok(()));
+}
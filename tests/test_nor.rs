@@ -0,0 +1,61 @@
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::nor::{Error, Geometry, NorFlash};
+
+static DETECTED: &str = "Loading Environment from SPIFlash... SF: Detected XM25QH64C with page size 256 Bytes, erase size 4 KiB, total 8 MiB\nOK\n";
+
+#[test]
+fn test_parse() {
+    let flash = NorFlash::parse(DETECTED).expect("Flash not found");
+
+    assert_eq!(flash.name, "XM25QH64C");
+    assert_eq!(
+        flash.geometry,
+        Geometry {
+            page_size: 256,
+            erase_size: 0x1000,
+            size: 0x80_0000
+        }
+    );
+    assert_eq!(flash.values()["nor_flash_erase_size"], "4 KiB");
+    assert_eq!(flash.values()["nor_flash_size"], "8 MiB");
+    assert_eq!(
+        flash.validate(&Config::default().qualified_nor_flashes()),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_parse_missing_flash() {
+    assert_eq!(
+        NorFlash::parse("Loading Environment from SPIFlash... OK\n"),
+        None
+    );
+}
+
+#[test]
+fn test_validate_unqualified() {
+    let flash =
+        NorFlash::parse(&DETECTED.replace("XM25QH64C", "W25Q64JV")).expect("Flash not found");
+
+    let error = flash
+        .validate(&Config::default().qualified_nor_flashes())
+        .expect_err("Unqualified flash accepted");
+
+    assert_eq!(error, Error::Unqualified(String::from("W25Q64JV")));
+    assert_eq!(error.to_string(), "Unqualified SPI-NOR flash W25Q64JV");
+}
+
+#[test]
+fn test_validate_wrong_geometry() {
+    let flash =
+        NorFlash::parse(&DETECTED.replace("total 8 MiB", "total 4 MiB")).expect("Flash not found");
+
+    let error = flash
+        .validate(&Config::default().qualified_nor_flashes())
+        .expect_err("Wrong geometry accepted");
+
+    assert_eq!(
+        error.to_string(),
+        "SPI-NOR flash XM25QH64C with wrong geometry (page size 256 Bytes, erase size 4 KiB, total 4 MiB, expected page size 256 Bytes, erase size 4 KiB, total 8 MiB)"
+    );
+}
@@ -0,0 +1,87 @@
+use smart_garden_gateway_doctor::analyzer::Diagnosis;
+use smart_garden_gateway_doctor::catalog::Catalog;
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::report::Report;
+
+#[test]
+fn test_save_report() {
+    let dir = std::env::temp_dir().join(format!("sggd-report-{}", std::process::id()));
+    let config = Config {
+        station: String::from("bench-1"),
+        serial_port: String::from("/dev/ttyUSB0"),
+        ..Default::default()
+    };
+    let diagnosis = Diagnosis {
+        message: String::from("No issues found"),
+        healthy: true,
+        ..Default::default()
+    };
+    let iprid = "0123abcd-0000-1111-2222-333344445555";
+
+    let report = Report::new(iprid, &config, &Catalog::default(), &diagnosis);
+    let path = report.save(&dir).expect("Failed to save report");
+
+    let content = std::fs::read_to_string(&path).expect("Failed to read report");
+    let json: serde_json::Value = serde_json::from_str(&content).expect("Invalid JSON");
+    std::fs::remove_dir_all(&dir).expect("Failed to remove report directory");
+
+    assert!(path
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with(iprid)));
+    assert_eq!(path.extension().and_then(|e| e.to_str()), Some("json"));
+    assert_eq!(json["iprid"], iprid);
+    assert_eq!(json["tool_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(json["station"]["name"], "bench-1");
+    assert_eq!(json["station"]["serial_port"], "/dev/ttyUSB0");
+    assert_eq!(json["message"], "No issues found");
+    assert_eq!(json["healthy"], true);
+    assert!(json["results"].is_array());
+    assert!(json["transcript"].is_array());
+}
+
+#[test]
+fn test_save_report_normalizes_iprid() {
+    let dir = std::env::temp_dir().join(format!("sggd-report-iprid-{}", std::process::id()));
+    let diagnosis = Diagnosis::default();
+
+    let report = Report::new(
+        "0123abcd'0000'1111'2222'333344445555",
+        &Config::default(),
+        &Catalog::default(),
+        &diagnosis,
+    );
+    let path = report.save(&dir).expect("Failed to save report");
+    std::fs::remove_dir_all(&dir).expect("Failed to remove report directory");
+
+    assert!(path
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with("0123abcd-0000-1111-2222-333344445555_")));
+}
+
+#[test]
+fn test_save_report_twice() {
+    let dir = std::env::temp_dir().join(format!("sggd-report-twice-{}", std::process::id()));
+    let diagnosis = Diagnosis::default();
+
+    let report = Report::new(
+        "0123abcd-0000-1111-2222-333344445555",
+        &Config::default(),
+        &Catalog::default(),
+        &diagnosis,
+    );
+    let first = report.save(&dir).expect("Failed to save report");
+    let second = report.save(&dir).expect("Failed to save report");
+    let count = std::fs::read_dir(&dir)
+        .expect("Failed to list report directory")
+        .count();
+    std::fs::remove_dir_all(&dir).expect("Failed to remove report directory");
+
+    assert_ne!(first, second);
+    assert_eq!(count, 2);
+    assert!(second
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.ends_with("_2")));
+}
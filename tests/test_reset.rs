@@ -0,0 +1,41 @@
+use smart_garden_gateway_doctor::reset::{count, excerpt, find_starts};
+
+#[test]
+fn test_find_starts() {
+    let console_output = "\
+U-Boot SPL 2021.04-gardena-6 (Jun 10 2021 - 16:05:31 +0000)
+Trying to boot from NOR
+DRAM:  128 MiB
+WDT:   Started with servicing (60s timeout)
+Net:   eth0: eth@10110000
+
+Hit any key to stop autoboot:  0
+U-Boot SPL 2021.04-gardena-6 (Jun 10 2021 - 16:05:31 +0000)
+Trying to boot from NOR
+### ERROR ### Please RESET the board ###
+U-Boot SPL 2021.04-gardena-6 (Jun 10 2021 - 16:05:31 +0000)
+";
+
+    let starts = find_starts(console_output);
+    let resets = &starts[1..];
+
+    assert_eq!(starts.len(), 3);
+    assert!(starts[0].last_lines.is_empty());
+    assert_eq!(
+        resets[0].last_lines,
+        [
+            "WDT:   Started with servicing (60s timeout)",
+            "Net:   eth0: eth@10110000",
+            "Hit any key to stop autoboot:  0"
+        ]
+    );
+    assert_eq!(count(resets), "2 resets");
+    assert!(excerpt(resets).ends_with(
+        "Hit any key to stop autoboot:  0\n\nTrying to boot from NOR\n### ERROR ### Please RESET the board ###"
+    ));
+}
+
+#[test]
+fn test_find_starts_without_banner() {
+    assert!(find_starts("Starting kernel ...\n").is_empty());
+}
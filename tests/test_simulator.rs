@@ -0,0 +1,167 @@
+use rstest::rstest;
+use serde::Deserialize;
+use smart_garden_gateway_doctor::analyzer::analyze;
+use smart_garden_gateway_doctor::catalog::Catalog;
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::simulator::{Scenario, Simulator, State};
+use smart_garden_gateway_doctor::transport::ScriptedTransport;
+
+#[derive(Deserialize)]
+struct TestData {
+    message: String,
+}
+
+fn scenario(case: &str) -> (Scenario, TestData) {
+    let file_path = std::path::PathBuf::from(format!(
+        "{}/tests/data/{case}.toml",
+        env!("CARGO_MANIFEST_DIR")
+    ));
+    let scenario = Scenario::load(&file_path)
+        .unwrap_or_else(|_| panic!("Failed to load scenario {}", &file_path.display()));
+    let file_content = std::fs::read_to_string(&file_path)
+        .unwrap_or_else(|_| panic!("Failed to read {}", &file_path.display()));
+    let test_data: TestData = toml::from_str(file_content.as_str())
+        .unwrap_or_else(|_| panic!("Failed to parse test data {}", &file_path.display()));
+
+    (scenario, test_data)
+}
+
+// The boot loop only repeats as time passes, see test_simulator_boot_loop
+#[rstest]
+#[test_log::test]
+fn test_simulator_plays_test_data(
+    #[values(
+        "button_stuck",
+        "factory_data_bad_crc",
+        "factory_data_prompt_in_dump",
+        "nand_worn",
+        "no_fdata",
+        "no_iprid",
+        "no_issues",
+        "no_nand",
+        "no_nand_button_stuck",
+        "no_phy",
+        "no_u-boot_prompt",
+        "no_u-boot",
+        "nor_flash_unqualified",
+        "reset_during_command",
+        "u-boot_env_bad_crc",
+        "u-boot_env_inconsistent_flags",
+        "u-boot_env_wrong_bootcmd",
+        "unexpected_reset",
+        "wrong_mtd_layout",
+        "wrong_ram_size"
+    )]
+    case: &str,
+) {
+    let (scenario, test_data) = scenario(case);
+    let mut simulator = Simulator::new(scenario);
+
+    let diagnosis = analyze(
+        &mut simulator,
+        &Catalog::default(),
+        &Config::default(),
+        None,
+    )
+    .expect("Failed to analyze");
+
+    assert_eq!(diagnosis.message, test_data.message);
+}
+
+#[rstest]
+#[test_log::test]
+fn test_simulator_boots_linux(
+    #[values("linux_env_bad_crc", "linux_no_issues", "linux_ubi_error")] case: &str,
+) {
+    let (scenario, test_data) = scenario(case);
+    let mut simulator = Simulator::new(scenario);
+    let config = Config {
+        linux: true,
+        linux_timeout: 1,
+        ..Default::default()
+    };
+
+    let diagnosis =
+        analyze(&mut simulator, &Catalog::default(), &config, None).expect("Failed to analyze");
+
+    assert_eq!(diagnosis.message, test_data.message);
+    assert_eq!(simulator.state(), State::Kernel);
+}
+
+#[test_log::test]
+fn test_simulator_shell() {
+    let (mut scenario, _) = scenario("no_issues");
+    scenario
+        .commands
+        .insert(String::from("version"), String::from("U-Boot 2021.04\n"));
+    let mut simulator = Simulator::new(scenario);
+
+    assert!(simulator.input(b"x").contains("DRAM:  128 MiB"));
+    assert_eq!(simulator.input(b"xxx"), "=> ");
+    assert_eq!(simulator.state(), State::Shell);
+
+    assert_eq!(simulator.input(b"x\x03"), "x<INTERRUPT>\n=> ");
+    assert_eq!(
+        simulator.input(b"version\n"),
+        "version\nU-Boot 2021.04\n=> "
+    );
+    assert!(simulator.input(b"mtd list\n").contains("spi-nand0"));
+    assert_eq!(
+        simulator.input(b"gpio input PA11\n"),
+        "gpio input PA11\ngpio: pin PA11 (gpio 11) value is 1\n=> "
+    );
+    assert!(simulator
+        .input(b"printenv linuxmoduleid\n")
+        .contains("linuxmoduleid=5d8c3b7e-2f41-4a96-8c0e-9b1f7a2d6e34"));
+    assert!(simulator
+        .input(b"md.b 0x84000000 0x1f0\n")
+        .contains("84000140: 42 e3 be 90 be ba fe ca"));
+    assert!(simulator
+        .input(b"mtd bad spi-nand0\n")
+        .contains("bad blocks list:\n\t0x03f60000\n"));
+    assert!(simulator
+        .input(b"printenv\n")
+        .contains("bootcmd=run boot_slot\n"));
+    assert!(simulator
+        .input(b"md.b 0x84000000 0x20\n")
+        .contains("84000010: 0b 8f 2e 46 00"));
+    assert_eq!(
+        simulator.input(b"help\n"),
+        "help\nUnknown command 'help' - try 'help'\n=> "
+    );
+
+    simulator.expire();
+    assert_eq!(simulator.state(), State::PoweredOff);
+}
+
+#[test_log::test]
+fn test_simulator_autoboot() {
+    let (scenario, _) = scenario("no_issues");
+    let mut simulator = Simulator::new(scenario);
+
+    simulator.input(b"x");
+    assert_eq!(simulator.state(), State::Autoboot);
+
+    assert!(simulator.expire().contains("Starting kernel"));
+    assert_eq!(simulator.state(), State::Kernel);
+}
+
+#[test_log::test]
+fn test_simulator_boot_loop() {
+    let (scenario, _) = scenario("boot_loop");
+    let mut simulator = Simulator::new(scenario);
+
+    let mut console_output = simulator.input(b"x");
+    assert_eq!(simulator.state(), State::BootLoop);
+    assert_eq!(simulator.input(b"x"), "");
+    console_output += &simulator.expire();
+
+    let diagnosis = analyze(
+        &mut ScriptedTransport::new([console_output]),
+        &Catalog::default(),
+        &Config::default(),
+        None,
+    )
+    .expect("Failed to analyze");
+    assert_eq!(diagnosis.message, "Boot loop (1 reset)");
+}
@@ -0,0 +1,93 @@
+use rstest::rstest;
+use smart_garden_gateway_doctor::analyzer::diagnose;
+use smart_garden_gateway_doctor::catalog::Catalog;
+use smart_garden_gateway_doctor::config::Config;
+use smart_garden_gateway_doctor::jig::{power_off_dut, power_on_dut, Error};
+use smart_garden_gateway_doctor::transport::{ConsoleTransport, ScriptedTransport, TcpTransport};
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+#[test]
+fn test_scripted_transport() {
+    let mut console = ScriptedTransport::new(["U-Boot SPL", "=> "]);
+    let mut buf = [0; 4];
+
+    assert_eq!(console.read(&mut buf, TIMEOUT).unwrap(), 4);
+    assert_eq!(&buf, b"U-Bo");
+    assert_eq!(console.read(&mut buf, TIMEOUT).unwrap(), 4);
+    assert_eq!(&buf, b"ot S");
+    assert_eq!(console.read(&mut buf, TIMEOUT).unwrap(), 2);
+    assert_eq!(&buf[..2], b"PL");
+    assert_eq!(console.read(&mut buf, TIMEOUT).unwrap(), 3);
+    assert_eq!(console.read(&mut buf, TIMEOUT).unwrap(), 0);
+
+    console.write_all(b"mtd list\n").unwrap();
+    assert_eq!(console.written(), b"mtd list\n");
+}
+
+#[test]
+fn test_power_line() {
+    let mut console = ScriptedTransport::default();
+
+    power_on_dut(&mut console, true).unwrap();
+    assert_eq!(console.power_line(), Some(false));
+    power_off_dut(&mut console, true).unwrap();
+    assert_eq!(console.power_line(), Some(true));
+}
+
+#[test]
+fn test_tcp_transport_strips_telnet_commands() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        // WILL ECHO, data with an escaped 0xff, subnegotiation, data
+        stream
+            .write_all(b"\xff\xfb\x01=>\xff\xff \xff\xfa\x2c\x01\xff\xf0ok")
+            .unwrap();
+        let mut reply = [0; 3];
+        stream.read_exact(&mut reply).unwrap();
+        reply
+    });
+
+    let mut console = TcpTransport::connect(addr).unwrap();
+    let mut received = Vec::new();
+    let mut buf = [0; 64];
+    while received.len() < 6 {
+        let n = console.read(&mut buf, TIMEOUT).unwrap();
+        received.extend_from_slice(&buf[..n]);
+    }
+
+    assert_eq!(received, b"=>\xff ok");
+    assert_eq!(server.join().unwrap(), [0xff, 0xfe, 0x01]);
+}
+
+#[test]
+fn test_tcp_transport_cannot_switch_power() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut console = TcpTransport::connect(addr).unwrap();
+
+    let error = power_on_dut(&mut console, true).expect_err("Power switched");
+
+    assert!(matches!(error, Error::PowerOn(e) if e.kind() == ErrorKind::Unsupported));
+}
+
+#[rstest]
+#[case::switched(true, Some(true))]
+#[case::external(false, None)]
+fn test_diagnose_power_control(#[case] power_control: bool, #[case] power_line: Option<bool>) {
+    let mut console = ScriptedTransport::default();
+    let config = Config {
+        power_control,
+        u_boot_timeout: 0,
+        ..Default::default()
+    };
+
+    diagnose(&mut console, &Catalog::default(), &config).expect("Failed to diagnose");
+
+    assert_eq!(console.power_line(), power_line);
+}